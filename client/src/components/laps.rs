@@ -2,7 +2,7 @@ use crate::components::charts::LapBoxChart;
 use crate::components::DetailProps;
 use crate::components::LapChart;
 use crate::components::Spinner;
-use crate::models::LapLineChartData;
+use crate::models::LapChartResponse;
 use crate::utils;
 use yew::prelude::*;
 
@@ -22,8 +22,8 @@ pub fn laps(props: &DetailProps) -> Html {
                     "http://localhost:3000/laps-chart?year={}&round={}&exclude_pitstop=true",
                     props.year, props.round
                 );
-                let response = utils::fetch_server::<Vec<LapLineChartData>>(&url).await;
-                data.set(Some(response));
+                let response = utils::fetch_server::<LapChartResponse>(&url).await;
+                data.set(Some(response.drivers));
             });
         });
     }
@@ -64,8 +64,8 @@ pub fn laps_box(props: &DetailProps) -> Html {
                     "http://localhost:3000/laps-chart?year={}&round={}&exclude_pitstop=true",
                     props.year, props.round
                 );
-                let response = utils::fetch_server::<Vec<LapLineChartData>>(&url).await;
-                data.set(Some(response));
+                let response = utils::fetch_server::<LapChartResponse>(&url).await;
+                data.set(Some(response.drivers));
             });
         });
     }
@@ -49,3 +49,17 @@ pub struct LapLineChartData {
     pub laptime: Vec<f64>,
     pub color: String,
 }
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapChartResponse {
+    pub fuel_per_lap: Option<f64>,
+    pub filters: Vec<LapChartFilter>,
+    pub drivers: Vec<LapLineChartData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LapChartFilter {
+    pub name: String,
+    pub description: String,
+    pub hidden_laps: usize,
+}
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE races
+    DROP COLUMN circuit_id,
+    DROP COLUMN circuit_name;
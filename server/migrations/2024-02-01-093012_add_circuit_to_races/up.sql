@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE races
+    ADD COLUMN circuit_id TEXT,
+    ADD COLUMN circuit_name TEXT;
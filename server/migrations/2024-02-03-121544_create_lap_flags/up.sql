@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE lap_flags (
+    id SERIAL PRIMARY KEY,
+    race_id INTEGER NOT NULL,
+    lap_number INTEGER NOT NULL,
+    field_median DOUBLE PRECISION NOT NULL,
+    neutralized BOOLEAN NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (race_id) REFERENCES races (id),
+    CONSTRAINT race_lap_number UNIQUE (race_id, lap_number)
+);
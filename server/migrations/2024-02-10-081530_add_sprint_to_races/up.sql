@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE races
+    ADD COLUMN sprint_time TIMESTAMP;
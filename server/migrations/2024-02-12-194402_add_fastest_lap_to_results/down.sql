@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE race_results
+    DROP COLUMN fastest_lap_rank;
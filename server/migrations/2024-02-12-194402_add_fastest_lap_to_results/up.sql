@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE race_results
+    ADD COLUMN fastest_lap_rank INTEGER;
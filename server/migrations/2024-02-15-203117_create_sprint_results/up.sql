@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE sprint_results (
+    id SERIAL PRIMARY KEY,
+    race_id INTEGER NOT NULL,
+    driver_id TEXT NOT NULL,
+    constructor_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    position_text TEXT NOT NULL,
+    points DECIMAL(3, 1) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (race_id) REFERENCES races (id),
+    FOREIGN KEY (driver_id) REFERENCES drivers (id),
+    FOREIGN KEY (constructor_id) REFERENCES constructors (id),
+    CONSTRAINT race_driver_for_sprint_results UNIQUE (race_id, driver_id)
+);
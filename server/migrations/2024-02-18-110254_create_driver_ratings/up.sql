@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE driver_ratings (
+    id SERIAL PRIMARY KEY,
+    race_id INTEGER NOT NULL,
+    driver_id TEXT NOT NULL,
+    rating DOUBLE PRECISION NOT NULL,
+    races INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (race_id) REFERENCES races (id),
+    FOREIGN KEY (driver_id) REFERENCES drivers (id),
+    CONSTRAINT race_driver_for_ratings UNIQUE (race_id, driver_id)
+);
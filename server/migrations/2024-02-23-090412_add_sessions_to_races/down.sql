@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE races
+    DROP COLUMN first_practice_time,
+    DROP COLUMN second_practice_time,
+    DROP COLUMN third_practice_time,
+    DROP COLUMN qualifying_time;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE races
+    ADD COLUMN first_practice_time TIMESTAMP,
+    ADD COLUMN second_practice_time TIMESTAMP,
+    ADD COLUMN third_practice_time TIMESTAMP,
+    ADD COLUMN qualifying_time TIMESTAMP;
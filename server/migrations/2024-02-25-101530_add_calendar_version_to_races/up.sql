@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE races
+    ADD COLUMN calendar_version INTEGER NOT NULL DEFAULT 0;
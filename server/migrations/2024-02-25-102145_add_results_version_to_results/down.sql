@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE race_results
+    DROP COLUMN results_version;
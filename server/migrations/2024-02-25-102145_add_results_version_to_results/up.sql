@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE race_results
+    ADD COLUMN results_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE rated_races (
+    id SERIAL PRIMARY KEY,
+    race_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    FOREIGN KEY (race_id) REFERENCES races (id),
+    CONSTRAINT race_for_rated_races UNIQUE (race_id)
+);
+
+INSERT INTO rated_races (race_id)
+    SELECT DISTINCT race_id FROM driver_ratings;
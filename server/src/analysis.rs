@@ -0,0 +1,20 @@
+pub mod championship;
+pub mod fuel;
+pub mod head_to_head;
+pub mod lap_chart;
+pub mod laps;
+pub mod matrix;
+pub mod neutralized;
+pub mod overtakes;
+pub mod pace;
+pub mod pit_analysis;
+pub mod points;
+pub mod race_trace;
+pub mod ratings;
+pub mod records;
+pub mod reliability;
+pub mod rescoring;
+pub mod simulation;
+pub mod standings;
+pub mod stats;
+pub mod stints;
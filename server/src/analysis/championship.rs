@@ -0,0 +1,219 @@
+//! Who can still win the drivers' championship, and when it can be decided.
+//!
+//! Works from the standings after the last completed round and the most points a driver
+//! can score on each remaining weekend: a win, the fastest lap point and, on sprint
+//! weekends, the sprint win. A tie on points is decided by countback, so it neither
+//! eliminates a driver nor clinches the title.
+use crate::analysis::points::PointsSystem;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Constructor, Driver, Race, Season, Standing};
+use crate::models::{ChampionshipResponse, DriverTitleChances};
+use std::time::SystemTime;
+
+struct Contender {
+    driver_id: String,
+    position: i32,
+    points: i32,
+}
+
+// best of the rivals' possible totals, when each of them scores `gain` on top of their points.
+fn best_rival(contenders: &[Contender], driver_id: &str, gain: i32) -> Option<i32> {
+    contenders
+        .iter()
+        .filter(|c| c.driver_id != driver_id)
+        .map(|c| c.points + gain)
+        .max()
+}
+
+// worst finishing position in the next race which clinches the title there, whatever the
+// rivals do. the driver is assumed not to score in the sprint or get the fastest lap.
+// a position past the points-scoring places means any result will do.
+fn clinch_finish(
+    contender: &Contender,
+    contenders: &[Contender],
+    system: &PointsSystem,
+    weekends: &[i32],
+) -> Option<i32> {
+    let (next, after) = weekends.split_first()?;
+    let after = after.iter().sum::<i32>();
+    let sprint = next - system.max_points(false);
+
+    (1..=system.race.len() as i32 + 1).rev().find(|&p| {
+        // a rival can at best win the race, or finish second behind the driver
+        let rival_finish = if p == 1 { 2 } else { 1 };
+        let rival_gain = system.race_points(rival_finish) + system.fastest_lap + sprint + after;
+        match best_rival(contenders, &contender.driver_id, rival_gain) {
+            Some(rival) => contender.points + system.race_points(p) > rival,
+            None => true,
+        }
+    })
+}
+
+// index into the remaining weekends after which the title can be decided at the earliest:
+// the driver scores the maximum on every weekend and the rivals score nothing.
+fn earliest_clinch(
+    contender: &Contender,
+    contenders: &[Contender],
+    weekends: &[i32],
+) -> Option<usize> {
+    (0..=weekends.len()).find(|&k| {
+        let gained = weekends[..k].iter().sum::<i32>();
+        let left = weekends[k..].iter().sum::<i32>();
+        match best_rival(contenders, &contender.driver_id, left) {
+            Some(rival) => contender.points + gained > rival,
+            None => true,
+        }
+    })
+}
+
+pub async fn generate_response(
+    season: &Season,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<ChampionshipResponse> {
+    let system = PointsSystem::for_season(season.season)?;
+    let completed = Race::get_completed_in_season(season, now, conn).await;
+    let remaining = Race::get_remaining_in_season(season, now, conn).await;
+
+    let mut standings = match completed.last() {
+        Some(race) => {
+            if !Standing::is_exist(race, conn) {
+                println!("Standing data is not in the database. Fetch from Ergast API.");
+                Standing::post(race, conn).await;
+            }
+            Standing::get(race, conn)
+        }
+        None => Vec::new(),
+    };
+    standings.sort_by_key(|s| s.position);
+
+    let weekends = remaining
+        .iter()
+        .map(|r| system.max_points(r.has_sprint()))
+        .collect::<Vec<i32>>();
+    let max_remaining = weekends.iter().sum::<i32>();
+    let contenders = standings
+        .iter()
+        .map(|s| Contender {
+            driver_id: s.driver_id.clone(),
+            position: s.position,
+            points: s.points,
+        })
+        .collect::<Vec<Contender>>();
+    let leader_points = contenders.first().map(|c| c.points).unwrap_or_default();
+
+    let mut drivers = Vec::new();
+    for (contender, standing) in contenders.iter().zip(&standings) {
+        let driver = Driver::get_by_id(&contender.driver_id, conn);
+        let constructor = Constructor::get_by_id(&standing.constructor_id, conn);
+        let alive = contender.points + max_remaining >= leader_points;
+        let earliest = if alive {
+            earliest_clinch(contender, &contenders, &weekends)
+        } else {
+            None
+        };
+        drivers.push(DriverTitleChances {
+            driver_id: contender.driver_id.clone(),
+            code: driver.code.unwrap_or("NA".to_string()),
+            color: constructor.team_color().to_string(),
+            position: contender.position,
+            points: contender.points,
+            max_points: contender.points + max_remaining,
+            alive,
+            clinched: earliest == Some(0),
+            clinch_finish: match earliest {
+                Some(k) if k > 0 => clinch_finish(contender, &contenders, &system, &weekends),
+                _ => None,
+            },
+            earliest_clinch_round: earliest.map(|k| match k {
+                0 => completed.last().map(|r| r.round()).unwrap_or_default(),
+                _ => remaining[k - 1].round(),
+            }),
+        });
+    }
+
+    Some(ChampionshipResponse {
+        season: season.season,
+        completed_rounds: completed.len(),
+        remaining_rounds: remaining.len(),
+        remaining_sprints: remaining.iter().filter(|r| r.has_sprint()).count(),
+        max_remaining_points: max_remaining,
+        drivers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contenders(points: &[(&str, i32)]) -> Vec<Contender> {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, (id, points))| Contender {
+                driver_id: id.to_string(),
+                position: i as i32 + 1,
+                points: *points,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn title_is_clinched_when_no_rival_can_catch_up() {
+        let field = contenders(&[("max", 200), ("per", 100)]);
+        assert_eq!(earliest_clinch(&field[0], &field, &[26, 26, 26]), Some(0));
+        assert_eq!(earliest_clinch(&field[1], &field, &[26, 26, 26]), None);
+    }
+
+    #[test]
+    fn tie_on_points_does_not_clinch() {
+        // the rival can draw level on 152, which countback decides
+        let field = contenders(&[("max", 152), ("per", 100)]);
+        assert_eq!(earliest_clinch(&field[0], &field, &[26, 26]), Some(1));
+    }
+
+    #[test]
+    fn remaining_sprints_keep_rivals_in_the_fight() {
+        let field = contenders(&[("max", 130), ("per", 100)]);
+        let system = PointsSystem::for_season(2023).unwrap();
+        let regular = [system.max_points(false)];
+        let sprint = [system.max_points(true)];
+        assert_eq!(earliest_clinch(&field[0], &field, &regular), Some(0));
+        // with the sprint the rival can still draw level, so only the last race decides it
+        assert_eq!(earliest_clinch(&field[0], &field, &sprint), Some(1));
+    }
+
+    #[test]
+    fn clinch_finish_is_the_worst_result_which_is_enough() {
+        let system = PointsSystem::for_season(2023).unwrap();
+        // the rival can score 26 in the next race and 26 after it: 60 + 52 = 112
+        let field = contenders(&[("max", 100), ("per", 60)]);
+        assert_eq!(
+            clinch_finish(&field[0], &field, &system, &[26, 26]),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn clinch_finish_counts_the_rival_behind_a_winner() {
+        let system = PointsSystem::for_season(2023).unwrap();
+        // a win clinches because the rival can then finish second at best
+        let field = contenders(&[("max", 100), ("per", 76)]);
+        assert_eq!(
+            clinch_finish(&field[0], &field, &system, &[26, 26]),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn clinch_finish_counts_the_sprint_of_the_next_weekend() {
+        let system = PointsSystem::for_season(2023).unwrap();
+        let field = contenders(&[("max", 100), ("per", 60)]);
+        let weekends = [system.max_points(true), 26];
+        // the rival's 8 sprint points take the bar to 120, out of reach of a podium
+        assert_eq!(
+            clinch_finish(&field[0], &field, &system, &weekends),
+            Some(1)
+        );
+    }
+}
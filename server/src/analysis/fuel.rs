@@ -0,0 +1,78 @@
+//! Fuel-corrected pace. A car gets lighter, and so faster, as it burns fuel. Correcting
+//! each lap time to an empty-tank equivalent makes early and late laps comparable:
+//!
+//! `corrected = time - per_lap * (total_laps - lap)`
+use crate::analysis::laps::DriverLaps;
+use crate::queries::FuelQuery;
+use std::collections::HashMap;
+use std::env;
+
+const DEFAULT_PER_LAP: f64 = 0.03; // seconds per lap
+
+/// per-lap fuel correction, configured by the environment:
+/// `FUEL_CORRECTION_PER_LAP` sets the default in seconds per lap and
+/// `FUEL_CORRECTION_OVERRIDES` overrides it per circuit, e.g. `monaco=0.02,spa=0.04`.
+#[derive(Clone, Debug)]
+pub struct FuelModel {
+    pub default_per_lap: f64,
+    pub overrides: HashMap<String, f64>,
+}
+
+impl FuelModel {
+    pub fn from_env() -> Self {
+        let default_per_lap = env::var("FUEL_CORRECTION_PER_LAP")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_PER_LAP);
+
+        let mut overrides = HashMap::new();
+        if let Ok(v) = env::var("FUEL_CORRECTION_OVERRIDES") {
+            for pair in v.split(',').filter(|p| !p.trim().is_empty()) {
+                match pair
+                    .split_once('=')
+                    .map(|(c, r)| (c.trim(), r.trim().parse::<f64>()))
+                {
+                    Some((circuit, Ok(rate))) => {
+                        overrides.insert(circuit.to_string(), rate);
+                    }
+                    _ => println!("Ignoring invalid fuel correction override: {}", pair),
+                }
+            }
+        }
+
+        Self {
+            default_per_lap,
+            overrides,
+        }
+    }
+
+    /// correction to apply for the request, or None if it is not fuel corrected.
+    /// an explicit `fuel_per_lap` wins over the circuit override and the default.
+    pub fn per_lap(&self, query: &FuelQuery, circuit_id: Option<&str>) -> Option<f64> {
+        if !query.fuel_corrected {
+            return None;
+        }
+        let rate = query.fuel_per_lap.unwrap_or_else(|| {
+            circuit_id
+                .and_then(|c| self.overrides.get(c).copied())
+                .unwrap_or(self.default_per_lap)
+        });
+        Some(rate)
+    }
+}
+
+pub fn correct(time: f64, lap: i32, total_laps: i32, per_lap: f64) -> f64 {
+    time - per_lap * (total_laps - lap) as f64
+}
+
+// correct every lap in place. the race distance is the highest lap number in the data.
+pub fn apply(drivers: &mut [DriverLaps], per_lap: f64) {
+    let total_laps = drivers
+        .iter()
+        .flat_map(|d| d.laps.iter().map(|l| l.lap))
+        .max()
+        .unwrap_or_default();
+    for lap in drivers.iter_mut().flat_map(|d| d.laps.iter_mut()) {
+        lap.time = correct(lap.time, lap.lap, total_laps, per_lap);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::analysis::stats;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Driver, Laptime};
+use crate::models::{HeadToHeadRace, HeadToHeadResponse, HeadToHeadSummary};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{Integer, Nullable, Numeric, Text};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[derive(QueryableByName)]
+struct SharedRaceRow {
+    #[diesel(sql_type = Integer)]
+    race_id: i32,
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Integer)]
+    round: i32,
+    #[diesel(sql_type = Text)]
+    race_name: String,
+    #[diesel(sql_type = Text)]
+    constructor_id: String,
+    #[diesel(sql_type = Integer)]
+    position_a: i32,
+    #[diesel(sql_type = Text)]
+    position_text_a: String,
+    #[diesel(sql_type = Numeric)]
+    points_a: BigDecimal,
+    #[diesel(sql_type = Integer)]
+    position_b: i32,
+    #[diesel(sql_type = Text)]
+    position_text_b: String,
+    #[diesel(sql_type = Numeric)]
+    points_b: BigDecimal,
+}
+
+// races where both drivers were entered by the same constructor.
+fn shared_races(
+    driver_a: &str,
+    driver_b: &str,
+    season: Option<i32>,
+    conn: &mut PooledConnection,
+) -> Result<Vec<SharedRaceRow>, Error> {
+    diesel::sql_query(
+        "SELECT r.id AS race_id, s.season, r.round, r.race_name, a.constructor_id,
+            a.position AS position_a, a.position_text AS position_text_a, a.points AS points_a,
+            b.position AS position_b, b.position_text AS position_text_b, b.points AS points_b
+        FROM race_results a
+        JOIN race_results b ON a.race_id = b.race_id AND a.constructor_id = b.constructor_id
+        JOIN races r ON a.race_id = r.id
+        JOIN seasons s ON r.season = s.id
+        WHERE a.driver_id = $1 AND b.driver_id = $2 AND ($3 IS NULL OR s.season = $3)
+        ORDER BY s.season, r.round",
+    )
+    .bind::<Text, _>(driver_a)
+    .bind::<Text, _>(driver_b)
+    .bind::<Nullable<Integer>, _>(season)
+    .load::<SharedRaceRow>(conn)
+}
+
+// lap time deltas (driver_a - driver_b, in seconds) keyed by race, for laps both drivers completed.
+fn lap_deltas(
+    race_ids: &[i32],
+    driver_a: &str,
+    driver_b: &str,
+    conn: &mut PooledConnection,
+) -> Result<HashMap<i32, Vec<f64>>, Error> {
+    use crate::db::schema::laptimes;
+
+    let laps = laptimes::table
+        .filter(laptimes::race_id.eq_any(race_ids))
+        .filter(laptimes::driver_id.eq_any([driver_a, driver_b]))
+        .load::<Laptime>(conn)?;
+
+    let mut times = HashMap::new();
+    for lap in &laps {
+        if let Ok(time) = Laptime::convert_lap_time_text_to_f64(&lap.lap_time) {
+            times.insert((lap.race_id, lap.driver_id.as_str(), lap.lap_number), time);
+        }
+    }
+
+    let mut deltas: HashMap<i32, Vec<f64>> = HashMap::new();
+    for lap in laps.iter().filter(|l| l.driver_id == driver_a) {
+        let a = times.get(&(lap.race_id, driver_a, lap.lap_number));
+        let b = times.get(&(lap.race_id, driver_b, lap.lap_number));
+        if let (Some(a), Some(b)) = (a, b) {
+            deltas.entry(lap.race_id).or_default().push(a - b);
+        }
+    }
+    Ok(deltas)
+}
+
+pub fn generate_response(
+    driver_a: &str,
+    driver_b: &str,
+    season: Option<i32>,
+    conn: &mut PooledConnection,
+) -> Result<Option<HeadToHeadResponse>, Error> {
+    if Driver::find_by_id(driver_a, conn).is_none() || Driver::find_by_id(driver_b, conn).is_none()
+    {
+        return Ok(None);
+    }
+
+    let shared = shared_races(driver_a, driver_b, season, conn)?;
+    let race_ids = shared.iter().map(|r| r.race_id).collect::<Vec<i32>>();
+    let deltas = lap_deltas(&race_ids, driver_a, driver_b, conn)?;
+
+    let mut summary = HeadToHeadSummary {
+        races: shared.len(),
+        ahead_a: 0,
+        ahead_b: 0,
+        ties: 0,
+        points_a: 0.0,
+        points_b: 0.0,
+        races_with_laps: deltas.len(),
+        lap_delta_median: stats::median(&deltas.values().flatten().copied().collect::<Vec<f64>>()),
+    };
+
+    let races = shared
+        .into_iter()
+        .map(|r| {
+            let points_a = r.points_a.to_f64().unwrap_or_default();
+            let points_b = r.points_b.to_f64().unwrap_or_default();
+            summary.points_a += points_a;
+            summary.points_b += points_b;
+
+            // race_results.position orders every entry, classified or not. shared cars of
+            // the early years can leave both drivers with the same one.
+            let ahead = match r.position_a.cmp(&r.position_b) {
+                Ordering::Less => {
+                    summary.ahead_a += 1;
+                    Some(driver_a.to_string())
+                }
+                Ordering::Greater => {
+                    summary.ahead_b += 1;
+                    Some(driver_b.to_string())
+                }
+                Ordering::Equal => {
+                    summary.ties += 1;
+                    None
+                }
+            };
+
+            HeadToHeadRace {
+                season: r.season,
+                round: r.round,
+                race_name: r.race_name,
+                constructor_id: r.constructor_id,
+                position_a: r.position_a,
+                position_text_a: r.position_text_a,
+                points_a,
+                position_b: r.position_b,
+                position_text_b: r.position_text_b,
+                points_b,
+                ahead,
+                lap_delta_median: deltas.get(&r.race_id).and_then(|d| stats::median(d)),
+            }
+        })
+        .collect::<Vec<HeadToHeadRace>>();
+
+    Ok(Some(HeadToHeadResponse {
+        driver_a: driver_a.to_string(),
+        driver_b: driver_b.to_string(),
+        season,
+        summary,
+        races,
+    }))
+}
@@ -0,0 +1,336 @@
+//! Lap time chart data with optional outlier filters. Every filter that hides laps is
+//! reported in the response, so charts can show what was left out.
+use crate::analysis::laps::{self, DriverLaps, LapSample};
+use crate::analysis::{fuel, neutralized, stats};
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::Race;
+use crate::models::{LapChartFilter, LapChartResponse, LapLineChartData};
+use crate::queries::{BestLap, LapChartQuery, NeutralizedLaps};
+use std::collections::{HashMap, HashSet};
+
+// keep the laps for which `keep` returns true and return the number of hidden laps.
+fn retain_laps<F>(drivers: &mut [DriverLaps], mut keep: F) -> usize
+where
+    F: FnMut(&str, &LapSample) -> bool,
+{
+    let mut hidden = 0;
+    for driver in drivers.iter_mut() {
+        let before = driver.laps.len();
+        let id = driver.driver_id.clone();
+        driver.laps.retain(|l| keep(&id, l));
+        hidden += before - driver.laps.len();
+    }
+    hidden
+}
+
+pub fn build(
+    mut drivers: Vec<DriverLaps>,
+    query: &LapChartQuery,
+    fuel_per_lap: Option<f64>,
+    neutralized_laps: &HashSet<i32>,
+) -> LapChartResponse {
+    let mut filters = Vec::new();
+    // taken before any filter, so that a stop on a hidden lap still hides the laps around it
+    let pit_laps = drivers
+        .iter()
+        .flat_map(|d| d.pit_laps().into_iter().map(|l| (d.driver_id.clone(), l)))
+        .collect::<HashSet<(String, i32)>>();
+
+    if let Some(ids) = query.driver_ids() {
+        let hidden = retain_laps(&mut drivers, |id, _| ids.iter().any(|i| i == id));
+        filters.push(LapChartFilter {
+            name: "drivers".to_string(),
+            description: format!("only laps of {}", ids.join(", ")),
+            hidden_laps: hidden,
+        });
+    }
+
+    if query.from_lap.is_some() || query.to_lap.is_some() {
+        let from = query.from_lap.unwrap_or(1);
+        let to = query.to_lap.unwrap_or(i32::MAX);
+        let hidden = retain_laps(&mut drivers, |_, l| (from..=to).contains(&l.lap));
+        filters.push(LapChartFilter {
+            name: "lap_range".to_string(),
+            description: match query.to_lap {
+                Some(to) => format!("only laps {} to {}", from, to),
+                None => format!("only laps from {}", from),
+            },
+            hidden_laps: hidden,
+        });
+    }
+
+    if query.exclude_first_lap {
+        let hidden = retain_laps(&mut drivers, |_, l| l.lap != 1);
+        filters.push(LapChartFilter {
+            name: "exclude_first_lap".to_string(),
+            description: "lap 1 with the standing start is hidden".to_string(),
+            hidden_laps: hidden,
+        });
+    }
+
+    if query.exclude_pitstop {
+        let hidden = retain_laps(&mut drivers, |id, l| {
+            !(l.lap - 1..=l.lap + 1).any(|p| pit_laps.contains(&(id.to_string(), p)))
+        });
+        filters.push(LapChartFilter {
+            name: "exclude_pitstop".to_string(),
+            description: "the lap before, the lap of and the lap after a pit stop are hidden"
+                .to_string(),
+            hidden_laps: hidden,
+        });
+    }
+
+    if query.neutralized == Some(NeutralizedLaps::Exclude) {
+        let hidden = retain_laps(&mut drivers, |_, l| !neutralized_laps.contains(&l.lap));
+        filters.push(LapChartFilter {
+            name: "neutralized".to_string(),
+            description: format!(
+                "safety car and VSC laps are hidden: the field median is more than {}% slower than the clean median",
+                neutralized::THRESHOLD * 100.0
+            ),
+            hidden_laps: hidden,
+        });
+    }
+
+    if let Some(max_percent) = query.max_percent {
+        let best = |laps: &[LapSample]| laps.iter().map(|l| l.time).reduce(f64::min);
+        let driver_best = drivers
+            .iter()
+            .filter_map(|d| best(&d.laps).map(|b| (d.driver_id.clone(), b)))
+            .collect::<HashMap<String, f64>>();
+        let field_best = driver_best.values().copied().reduce(f64::min);
+        let hidden = retain_laps(&mut drivers, |id, l| {
+            let reference = match query.percent_of {
+                BestLap::Field => field_best,
+                BestLap::Driver => driver_best.get(id).copied(),
+            };
+            match reference {
+                Some(r) => l.time <= r * max_percent / 100.0,
+                None => true,
+            }
+        });
+        filters.push(LapChartFilter {
+            name: "max_percent".to_string(),
+            description: match query.percent_of {
+                BestLap::Field => format!(
+                    "laps slower than {}% of the fastest lap of the field are hidden",
+                    max_percent
+                ),
+                BestLap::Driver => format!(
+                    "laps slower than {}% of the driver's own fastest lap are hidden",
+                    max_percent
+                ),
+            },
+            hidden_laps: hidden,
+        });
+    }
+
+    if let Some(max_z) = query.max_z_score {
+        let distributions = drivers
+            .iter()
+            .filter_map(|d| {
+                let times = d.laps.iter().map(|l| l.time).collect::<Vec<f64>>();
+                let mean = stats::mean(&times)?;
+                let sd = stats::std_dev(&times)?;
+                Some((d.driver_id.clone(), (mean, sd)))
+            })
+            .collect::<HashMap<String, (f64, f64)>>();
+        let hidden = retain_laps(&mut drivers, |id, l| match distributions.get(id) {
+            Some((mean, sd)) if *sd > 0.0 => ((l.time - mean) / sd).abs() <= max_z,
+            _ => true,
+        });
+        filters.push(LapChartFilter {
+            name: "max_z_score".to_string(),
+            description: format!(
+                "laps more than {} standard deviations away from the driver's mean lap are hidden",
+                max_z
+            ),
+            hidden_laps: hidden,
+        });
+    }
+
+    let drivers = drivers
+        .into_iter()
+        .filter(|d| !d.laps.is_empty())
+        .map(|d| LapLineChartData {
+            neutralized_laps: (query.neutralized == Some(NeutralizedLaps::Annotate)).then(|| {
+                d.laps
+                    .iter()
+                    .map(|l| l.lap)
+                    .filter(|l| neutralized_laps.contains(l))
+                    .collect()
+            }),
+            driver_id: d.driver_id,
+            position: d.result_position,
+            laps: d.laps.iter().map(|l| l.lap).collect(),
+            laptime: d.laps.iter().map(|l| l.time).collect(),
+            color: d.color,
+        })
+        .collect();
+
+    LapChartResponse {
+        fuel_per_lap,
+        filters,
+        drivers,
+    }
+}
+
+pub async fn generate_response(
+    race: &Race,
+    query: &LapChartQuery,
+    fuel_per_lap: Option<f64>,
+    conn: &mut PooledConnection,
+) -> LapChartResponse {
+    let mut drivers = laps::load(race, conn).await;
+    if let Some(per_lap) = fuel_per_lap {
+        fuel::apply(&mut drivers, per_lap);
+    }
+    let neutralized_laps = match query.neutralized {
+        Some(_) => neutralized::neutralized_laps(race, conn).await,
+        None => HashSet::new(),
+    };
+    build(drivers, query, fuel_per_lap, &neutralized_laps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn driver(id: &str, times: &[f64], pit_laps: &[i32]) -> DriverLaps {
+        DriverLaps {
+            driver_id: id.to_string(),
+            code: id.to_uppercase(),
+            color: "#000000".to_string(),
+            grid: 1,
+            result_position: 1,
+            laps: times
+                .iter()
+                .enumerate()
+                .map(|(i, &time)| {
+                    let lap = i as i32 + 1;
+                    LapSample {
+                        lap,
+                        time,
+                        position: 1,
+                        pit_in: pit_laps.contains(&lap),
+                        pit_out: pit_laps.contains(&(lap - 1)),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn query(value: serde_json::Value) -> LapChartQuery {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn laps_of(response: &LapChartResponse, id: &str) -> Vec<i32> {
+        response
+            .drivers
+            .iter()
+            .find(|d| d.driver_id == id)
+            .map(|d| d.laps.clone())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn flags_default_to_false() {
+        let query = query(json!({}));
+        assert!(!query.exclude_first_lap);
+        assert!(!query.exclude_pitstop);
+    }
+
+    #[test]
+    fn out_lap_is_hidden_when_the_stop_is_outside_the_lap_range() {
+        let drivers = vec![driver("max", &[90.0; 12], &[9])];
+        let response = build(
+            drivers,
+            &query(json!({ "from_lap": 10, "exclude_pitstop": true })),
+            None,
+            &HashSet::new(),
+        );
+        assert_eq!(laps_of(&response, "max"), vec![11, 12]);
+        assert_eq!(response.filters[0].hidden_laps, 9);
+        assert_eq!(response.filters[1].name, "exclude_pitstop");
+        assert_eq!(response.filters[1].hidden_laps, 1);
+    }
+
+    #[test]
+    fn laps_around_a_stop_are_hidden() {
+        let drivers = vec![driver("max", &[90.0; 6], &[3])];
+        let response = build(
+            drivers,
+            &query(json!({ "exclude_pitstop": true })),
+            None,
+            &HashSet::new(),
+        );
+        assert_eq!(laps_of(&response, "max"), vec![1, 5, 6]);
+    }
+
+    #[test]
+    fn driver_and_first_lap_filters_count_hidden_laps() {
+        let drivers = vec![
+            driver("max", &[95.0, 90.0, 90.0], &[]),
+            driver("lec", &[96.0, 91.0, 91.0], &[]),
+        ];
+        let response = build(
+            drivers,
+            &query(json!({ "drivers": "max", "exclude_first_lap": true })),
+            None,
+            &HashSet::new(),
+        );
+        assert_eq!(response.drivers.len(), 1);
+        assert_eq!(laps_of(&response, "max"), vec![2, 3]);
+        assert_eq!(response.filters[0].hidden_laps, 3);
+        assert_eq!(response.filters[1].hidden_laps, 1);
+    }
+
+    #[test]
+    fn max_percent_is_relative_to_the_field_or_the_driver() {
+        let drivers = || {
+            vec![
+                driver("max", &[90.0, 95.0], &[]),
+                driver("lec", &[98.0, 100.0], &[]),
+            ]
+        };
+        let field = build(
+            drivers(),
+            &query(json!({ "max_percent": 107.0 })),
+            None,
+            &HashSet::new(),
+        );
+        assert_eq!(laps_of(&field, "lec"), Vec::<i32>::new());
+        assert_eq!(field.filters[0].hidden_laps, 2);
+
+        let own = build(
+            drivers(),
+            &query(json!({ "max_percent": 103.0, "percent_of": "driver" })),
+            None,
+            &HashSet::new(),
+        );
+        assert_eq!(laps_of(&own, "max"), vec![1]);
+        assert_eq!(laps_of(&own, "lec"), vec![1, 2]);
+    }
+
+    #[test]
+    fn neutralized_laps_are_hidden_or_annotated() {
+        let neutralized = HashSet::from([2]);
+        let excluded = build(
+            vec![driver("max", &[90.0, 120.0, 90.0], &[])],
+            &query(json!({ "neutralized": "exclude" })),
+            None,
+            &neutralized,
+        );
+        assert_eq!(laps_of(&excluded, "max"), vec![1, 3]);
+
+        let annotated = build(
+            vec![driver("max", &[90.0, 120.0, 90.0], &[])],
+            &query(json!({ "neutralized": "annotate" })),
+            None,
+            &neutralized,
+        );
+        assert!(annotated.filters.is_empty());
+        assert_eq!(annotated.drivers[0].neutralized_laps, Some(vec![2]));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Constructor, Laptime, Race};
+use std::collections::{HashMap, HashSet};
+
+/// one timed lap of a driver. `pit_in` is the lap the driver entered the pits on and
+/// `pit_out` the lap after it.
+#[derive(Debug, Clone)]
+pub struct LapSample {
+    pub lap: i32,
+    pub time: f64,
+    pub position: i32,
+    pub pit_in: bool,
+    pub pit_out: bool,
+}
+
+impl LapSample {
+    // laps which represent the pace of the car: not the standing start, an in-lap or an out-lap.
+    pub fn is_clean(&self) -> bool {
+        self.lap > 1 && !self.pit_in && !self.pit_out
+    }
+}
+
+/// stored laps of a driver in one race, in lap order.
+#[derive(Debug, Clone)]
+pub struct DriverLaps {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub grid: i32, // 0 is a pit lane start
+    pub result_position: i32,
+    pub laps: Vec<LapSample>,
+}
+
+impl DriverLaps {
+    pub fn pit_laps(&self) -> Vec<i32> {
+        self.laps
+            .iter()
+            .filter(|l| l.pit_in)
+            .map(|l| l.lap)
+            .collect()
+    }
+}
+
+// load lap data of the race per driver, fetching it from Ergast API if it is missing.
+// drivers are ordered by result position.
+pub async fn load(race: &Race, conn: &mut PooledConnection) -> Vec<DriverLaps> {
+    Laptime::fetch_if_missing(race, conn).await;
+
+    let rows = Laptime::get(race, conn);
+    let pit_in_laps = rows
+        .iter()
+        .filter_map(|(_, pitstop, _, _)| pitstop.as_ref())
+        .map(|p| (p.driver_id.clone(), p.lap_number))
+        .collect::<HashSet<(String, i32)>>();
+
+    let mut map: HashMap<String, DriverLaps> = HashMap::new();
+    for (laptime, _pitstop, driver, race_result) in rows {
+        let time = match Laptime::convert_lap_time_text_to_f64(&laptime.lap_time) {
+            Ok(t) => t,
+            Err(e) => {
+                println!(
+                    "Skipping lap {} of {}: {}",
+                    laptime.lap_number, driver.id, e
+                );
+                continue;
+            }
+        };
+        let entry = map.entry(driver.id.clone()).or_insert_with(|| {
+            let constructor = Constructor::get_by_id(&race_result.constructor_id, conn);
+            DriverLaps {
+                driver_id: driver.id.clone(),
+                code: driver.code.clone().unwrap_or("NA".to_string()),
+                color: constructor.team_color().to_string(),
+                grid: race_result.grid,
+                result_position: race_result.position,
+                laps: Vec::new(),
+            }
+        });
+        entry.laps.push(LapSample {
+            lap: laptime.lap_number,
+            time,
+            position: laptime.position,
+            pit_in: pit_in_laps.contains(&(driver.id.clone(), laptime.lap_number)),
+            pit_out: pit_in_laps.contains(&(driver.id.clone(), laptime.lap_number - 1)),
+        });
+    }
+
+    let mut drivers = map.into_values().collect::<Vec<DriverLaps>>();
+    for d in drivers.iter_mut() {
+        d.laps.sort_by_key(|l| l.lap);
+    }
+    drivers.sort_by_key(|d| d.result_position);
+    drivers
+}
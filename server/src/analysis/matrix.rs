@@ -0,0 +1,117 @@
+//! Drivers by rounds grid of a season's results, for the season results heatmap.
+//!
+//! The cells are the grand prix results of the completed rounds, loaded with their drivers
+//! in one query. Drivers are in the order of the standings computed from the same rows and
+//! the sprint results, so sprint points count.
+use crate::analysis::standings;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Constructor, RaceResult, Season, SprintResult};
+use crate::models::{MatrixCell, MatrixRound, MatrixRow, SeasonMatrixResponse};
+use bigdecimal::ToPrimitive;
+use diesel::prelude::*;
+use diesel::result::Error;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+// category of a result by its position_text, for coloring the cell.
+fn category(position_text: &str) -> &'static str {
+    match position_text {
+        "R" => "retired",
+        "D" | "E" => "disqualified",
+        "N" => "not_classified",
+        "W" => "withdrawn",
+        "F" => "did_not_qualify",
+        _ => "classified",
+    }
+}
+
+pub async fn generate_response(
+    season: &Season,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Result<Option<SeasonMatrixResponse>, Error> {
+    use crate::db::schema::{constructors, drivers, race_results, races, sprint_results};
+
+    let race_ids = match standings::completed_races(season, i32::MAX, now, conn).await {
+        Some(races) => races.iter().map(|r| r.id()).collect::<Vec<i32>>(),
+        None => return Ok(None),
+    };
+
+    let rows = race_results::table
+        .inner_join(races::table)
+        .inner_join(drivers::table)
+        .inner_join(constructors::table)
+        .filter(race_results::race_id.eq_any(&race_ids))
+        .order((races::round.asc(), race_results::position.asc()))
+        .select((
+            RaceResult::as_select(),
+            races::round,
+            races::race_name,
+            drivers::code,
+            Constructor::as_select(),
+        ))
+        .load::<(RaceResult, i32, String, Option<String>, Constructor)>(conn)?;
+    let sprints = sprint_results::table
+        .filter(sprint_results::race_id.eq_any(&race_ids))
+        .select(SprintResult::as_select())
+        .load::<SprintResult>(conn)?;
+    let standings = standings::rank_drivers(rows.iter().map(|(r, ..)| r), &sprints);
+
+    let mut rounds: Vec<MatrixRound> = Vec::new();
+    for (_, round, race_name, ..) in &rows {
+        if rounds.last().map(|r| r.round) != Some(*round) {
+            rounds.push(MatrixRound {
+                round: *round,
+                race_name: race_name.clone(),
+            });
+        }
+    }
+
+    // code and team color of each driver, from their latest round
+    let mut labels: HashMap<String, (String, String)> = HashMap::new();
+    let mut cells: HashMap<String, Vec<Option<MatrixCell>>> = HashMap::new();
+    for (result, round, _, code, constructor) in rows {
+        labels.insert(
+            result.driver_id.clone(),
+            (
+                code.unwrap_or("NA".to_string()),
+                constructor.team_color().to_string(),
+            ),
+        );
+        let index = rounds
+            .iter()
+            .position(|r| r.round == round)
+            .unwrap_or_default();
+        let row = cells
+            .entry(result.driver_id.clone())
+            .or_insert_with(|| vec![None; rounds.len()]);
+        row[index] = Some(MatrixCell {
+            position: result.is_classified().then_some(result.position),
+            category: category(&result.position_text).to_string(),
+            points: result.points.to_f64().unwrap_or_default(),
+            position_text: result.position_text,
+        });
+    }
+
+    // drivers with sprint results only have no grand prix row to label them
+    let drivers = standings
+        .into_iter()
+        .filter_map(|(position, id, points)| {
+            let (code, color) = labels.remove(&id)?;
+            Some(MatrixRow {
+                cells: cells.remove(&id)?,
+                driver_id: id,
+                code,
+                color,
+                position,
+                points,
+            })
+        })
+        .collect();
+
+    Ok(Some(SeasonMatrixResponse {
+        season: season.season,
+        rounds,
+        drivers,
+    }))
+}
@@ -0,0 +1,74 @@
+//! Safety car and virtual safety car detection. Neutralized laps slow the whole field
+//! down, so a lap is flagged when the field-wide median lap time is more than
+//! `THRESHOLD` slower than the race's clean median.
+use crate::analysis::laps::{self, DriverLaps};
+use crate::analysis::stats;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{LapFlag, NewLapFlag, Race};
+use std::collections::{BTreeMap, HashSet};
+
+pub const THRESHOLD: f64 = 0.15;
+
+/// median lap time of the field on one lap, excluding in-laps and out-laps.
+pub struct FieldLap {
+    pub lap: i32,
+    pub field_median: f64,
+    pub neutralized: bool,
+}
+
+pub fn detect(drivers: &[DriverLaps]) -> Vec<FieldLap> {
+    let mut times: BTreeMap<i32, Vec<f64>> = BTreeMap::new();
+    for lap in drivers.iter().flat_map(|d| &d.laps) {
+        if !lap.pit_in && !lap.pit_out {
+            times.entry(lap.lap).or_default().push(lap.time);
+        }
+    }
+
+    let medians = times
+        .into_iter()
+        .filter_map(|(lap, t)| stats::median(&t).map(|m| (lap, m)))
+        .collect::<Vec<(i32, f64)>>();
+
+    // the standing start lap is slow for everyone, so it does not count for the clean pace
+    let clean = medians
+        .iter()
+        .filter(|(lap, _)| *lap > 1)
+        .map(|(_, m)| *m)
+        .collect::<Vec<f64>>();
+    let clean_median = match stats::median(&clean) {
+        Some(m) => m,
+        None => return Vec::new(),
+    };
+
+    medians
+        .into_iter()
+        .map(|(lap, field_median)| FieldLap {
+            lap,
+            field_median,
+            neutralized: field_median > clean_median * (1.0 + THRESHOLD),
+        })
+        .collect()
+}
+
+// neutralized laps of the race. flags are detected once and stored per race.
+pub async fn neutralized_laps(race: &Race, conn: &mut PooledConnection) -> HashSet<i32> {
+    if !LapFlag::is_exist(race, conn) {
+        let drivers = laps::load(race, conn).await;
+        let flags = detect(&drivers)
+            .into_iter()
+            .map(|f| NewLapFlag {
+                race_id: race.id(),
+                lap_number: f.lap,
+                field_median: f.field_median,
+                neutralized: f.neutralized,
+            })
+            .collect::<Vec<NewLapFlag>>();
+        LapFlag::post(race, &flags, conn);
+    }
+
+    LapFlag::get(race, conn)
+        .into_iter()
+        .filter(|f| f.neutralized)
+        .map(|f| f.lap_number)
+        .collect()
+}
@@ -0,0 +1,144 @@
+//! Overtaking and position-change statistics from the per-lap positions.
+//!
+//! A pass is counted when a driver is behind another at the end of a lap and ahead of
+//! them at the end of the next. Changes where either driver was on an in-lap or out-lap
+//! are explained by the pit stop and not counted. Lap 1 is covered by the first-lap gain.
+use crate::analysis::laps::{self, DriverLaps, LapSample};
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Race, Season};
+use crate::models::{DriverRaceStats, RaceStatsResponse};
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+
+fn is_pit_affected(lap: &LapSample) -> bool {
+    lap.pit_in || lap.pit_out
+}
+
+// on-track passes made and lost per driver, indexed like `drivers`.
+fn count_passes(drivers: &[DriverLaps]) -> (Vec<i32>, Vec<i32>) {
+    let mut by_lap: BTreeMap<i32, HashMap<usize, &LapSample>> = BTreeMap::new();
+    for (i, driver) in drivers.iter().enumerate() {
+        for lap in &driver.laps {
+            by_lap.entry(lap.lap).or_default().insert(i, lap);
+        }
+    }
+
+    let mut made = vec![0; drivers.len()];
+    let mut lost = vec![0; drivers.len()];
+    for (lap, current) in &by_lap {
+        let previous = match by_lap.get(&(lap - 1)) {
+            Some(p) if *lap > 1 => p,
+            _ => continue,
+        };
+        for (a, lap_a) in current {
+            for (b, lap_b) in current {
+                let (prev_a, prev_b) = match (previous.get(a), previous.get(b)) {
+                    (Some(pa), Some(pb)) => (pa, pb),
+                    _ => continue,
+                };
+                let passed = prev_a.position > prev_b.position && lap_a.position < lap_b.position;
+                if passed && !is_pit_affected(lap_a) && !is_pit_affected(lap_b) {
+                    made[*a] += 1;
+                    lost[*b] += 1;
+                }
+            }
+        }
+    }
+    (made, lost)
+}
+
+pub fn race_stats(drivers: &[DriverLaps]) -> Vec<DriverRaceStats> {
+    let (made, lost) = count_passes(drivers);
+    // a pit lane start counts as starting from the back of the field
+    let field_size = drivers.len() as i32;
+
+    drivers
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            let grid = if d.grid > 0 { d.grid } else { field_size };
+            let first_lap = d.laps.iter().find(|l| l.lap == 1).map(|l| l.position);
+            DriverRaceStats {
+                driver_id: d.driver_id.clone(),
+                code: d.code.clone(),
+                color: d.color.clone(),
+                races: 1,
+                grid: Some(grid),
+                finish: Some(d.result_position),
+                places_gained: grid - d.result_position,
+                first_lap_gain: first_lap.map(|p| grid - p).unwrap_or_default(),
+                passes_made: made[i],
+                passes_lost: lost[i],
+                laps_led: d.laps.iter().filter(|l| l.position == 1).count() as i32,
+            }
+        })
+        .collect()
+}
+
+// sum of the race statistics of every driver over the races.
+fn season_stats(races: Vec<Vec<DriverRaceStats>>) -> Vec<DriverRaceStats> {
+    let mut map: HashMap<String, DriverRaceStats> = HashMap::new();
+    for stats in races.into_iter().flatten() {
+        match map.get_mut(&stats.driver_id) {
+            Some(entry) => {
+                entry.races += 1;
+                entry.color = stats.color; // latest team
+                entry.places_gained += stats.places_gained;
+                entry.first_lap_gain += stats.first_lap_gain;
+                entry.passes_made += stats.passes_made;
+                entry.passes_lost += stats.passes_lost;
+                entry.laps_led += stats.laps_led;
+            }
+            None => {
+                map.insert(
+                    stats.driver_id.clone(),
+                    DriverRaceStats {
+                        grid: None,
+                        finish: None,
+                        ..stats
+                    },
+                );
+            }
+        }
+    }
+    let mut vec = map.into_values().collect::<Vec<DriverRaceStats>>();
+    vec.sort_by_key(|d| std::cmp::Reverse(d.passes_made));
+    vec
+}
+
+pub async fn generate_race_response(
+    season: &Season,
+    race: &Race,
+    conn: &mut PooledConnection,
+) -> RaceStatsResponse {
+    let drivers = laps::load(race, conn).await;
+    let stats = race_stats(&drivers);
+    RaceStatsResponse {
+        season: season.season,
+        round: Some(race.round()),
+        races: 1,
+        total_passes: stats.iter().map(|s| s.passes_made).sum(),
+        drivers: stats,
+    }
+}
+
+pub async fn generate_season_response(
+    season: &Season,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> RaceStatsResponse {
+    let races = Race::get_completed_in_season(season, now, conn).await;
+    let mut per_race = Vec::new();
+    for race in &races {
+        let drivers = laps::load(race, conn).await;
+        per_race.push(race_stats(&drivers));
+    }
+    let stats = season_stats(per_race);
+    RaceStatsResponse {
+        season: season.season,
+        round: None,
+        races: races.len(),
+        total_passes: stats.iter().map(|s| s.passes_made).sum(),
+        drivers: stats,
+    }
+}
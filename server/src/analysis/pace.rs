@@ -0,0 +1,118 @@
+//! Race pace and consistency ranking from the clean laps of each driver.
+//!
+//! Only laps which represent the pace of the car count: no lap 1, in-laps, out-laps or
+//! laps under the safety car or VSC. A driver needs `MIN_CLEAN_LAPS` of them to be ranked.
+use crate::analysis::laps::{self, DriverLaps};
+use crate::analysis::neutralized;
+use crate::analysis::stats;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Race, Season};
+use crate::models::{DriverPace, DriverSeasonPace, RacePaceResponse, SeasonPaceResponse};
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+const MIN_CLEAN_LAPS: usize = 5;
+
+fn driver_pace(driver: &DriverLaps, neutralized: &HashSet<i32>) -> Option<DriverPace> {
+    let times = driver
+        .laps
+        .iter()
+        .filter(|l| l.is_clean() && !neutralized.contains(&l.lap))
+        .map(|l| l.time)
+        .collect::<Vec<f64>>();
+    if times.len() < MIN_CLEAN_LAPS {
+        return None;
+    }
+    Some(DriverPace {
+        driver_id: driver.driver_id.clone(),
+        code: driver.code.clone(),
+        color: driver.color.clone(),
+        rank: 0,
+        clean_laps: times.len(),
+        median: stats::median(&times)?,
+        std_dev: stats::std_dev(&times)?,
+        iqr: stats::quantile(&times, 0.75)? - stats::quantile(&times, 0.25)?,
+        percent_off: 0.0,
+    })
+}
+
+// drivers ranked by median clean lap, fastest first.
+pub fn rank(drivers: &[DriverLaps], neutralized: &HashSet<i32>) -> Vec<DriverPace> {
+    let mut vec = drivers
+        .iter()
+        .filter_map(|d| driver_pace(d, neutralized))
+        .collect::<Vec<DriverPace>>();
+    vec.sort_by(|a, b| a.median.total_cmp(&b.median));
+
+    let fastest = vec.first().map(|p| p.median).unwrap_or_default();
+    for (i, pace) in vec.iter_mut().enumerate() {
+        pace.rank = i as i32 + 1;
+        pace.percent_off = (pace.median / fastest - 1.0) * 100.0;
+    }
+    vec
+}
+
+// averages over the races each driver was ranked in. the consistency index is the mean
+// coefficient of variation (std dev over median, in percent), so lower is more consistent.
+fn season_pace(races: Vec<Vec<DriverPace>>) -> Vec<DriverSeasonPace> {
+    let mut map: HashMap<String, Vec<DriverPace>> = HashMap::new();
+    for pace in races.into_iter().flatten() {
+        map.entry(pace.driver_id.clone()).or_default().push(pace);
+    }
+
+    let mut vec = map
+        .into_values()
+        .filter_map(|paces| {
+            let latest = paces.last()?;
+            let percent_off = paces.iter().map(|p| p.percent_off).collect::<Vec<f64>>();
+            let variation = paces
+                .iter()
+                .map(|p| p.std_dev / p.median * 100.0)
+                .collect::<Vec<f64>>();
+            Some(DriverSeasonPace {
+                driver_id: latest.driver_id.clone(),
+                code: latest.code.clone(),
+                color: latest.color.clone(), // latest team
+                races: paces.len(),
+                average_percent_off: stats::mean(&percent_off)?,
+                consistency_index: stats::mean(&variation)?,
+                fastest_races: paces.iter().filter(|p| p.rank == 1).count(),
+            })
+        })
+        .collect::<Vec<DriverSeasonPace>>();
+    vec.sort_by(|a, b| a.average_percent_off.total_cmp(&b.average_percent_off));
+    vec
+}
+
+pub async fn generate_race_response(
+    season: &Season,
+    race: &Race,
+    conn: &mut PooledConnection,
+) -> RacePaceResponse {
+    let drivers = laps::load(race, conn).await;
+    let neutralized = neutralized::neutralized_laps(race, conn).await;
+    RacePaceResponse {
+        season: season.season,
+        round: race.round(),
+        drivers: rank(&drivers, &neutralized),
+    }
+}
+
+pub async fn generate_season_response(
+    season: &Season,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> SeasonPaceResponse {
+    let races = Race::get_completed_in_season(season, now, conn).await;
+    let mut per_race = Vec::new();
+    for race in &races {
+        let drivers = laps::load(race, conn).await;
+        let neutralized = neutralized::neutralized_laps(race, conn).await;
+        per_race.push(rank(&drivers, &neutralized));
+    }
+    SeasonPaceResponse {
+        season: season.season,
+        races: races.len(),
+        drivers: season_pace(per_race),
+    }
+}
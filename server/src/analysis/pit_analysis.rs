@@ -0,0 +1,164 @@
+//! Pit stop loss and strategy comparison of a race.
+//!
+//! The loss of a stop is the time the in-lap and the out-lap took over the driver's clean
+//! pace (median of the laps without lap 1, in-laps and out-laps). That already contains
+//! the time spent in the pit lane, stationary time included, which Ergast reports as the
+//! stop's duration. Ergast has no separate stationary time, so `lap_loss` is the whole
+//! loss rather than lap time plus stationary time.
+use crate::analysis::laps::{self, DriverLaps};
+use crate::analysis::race_trace::cumulative_times;
+use crate::analysis::stats;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Pitstop, Race};
+use crate::models::{DriverStrategy, PitAnalysisResponse, PitBattle, PitStopLoss};
+use bigdecimal::ToPrimitive;
+use std::collections::HashMap;
+
+// drivers within this gap before the first of their stops were fighting on track
+const CLOSE_GAP: f64 = 3.0; // seconds
+
+// stops further apart than this are different strategies rather than a pit battle
+const MAX_STOP_WINDOW: i32 = 5; // laps
+
+struct DriverView<'a> {
+    laps: &'a DriverLaps,
+    cumulative: HashMap<i32, f64>,
+}
+
+impl DriverView<'_> {
+    fn position(&self, lap: i32) -> Option<i32> {
+        self.laps
+            .laps
+            .iter()
+            .find(|l| l.lap == lap)
+            .map(|l| l.position)
+    }
+
+    fn lap_time(&self, lap: i32) -> Option<f64> {
+        self.laps.laps.iter().find(|l| l.lap == lap).map(|l| l.time)
+    }
+}
+
+fn stop_losses(views: &[DriverView], pitstops: &[Pitstop]) -> Vec<PitStopLoss> {
+    let mut vec = Vec::new();
+    for view in views {
+        let clean = view
+            .laps
+            .laps
+            .iter()
+            .filter(|l| l.is_clean())
+            .map(|l| l.time)
+            .collect::<Vec<f64>>();
+        let pace = stats::median(&clean);
+
+        let mut stops = pitstops
+            .iter()
+            .filter(|p| p.driver_id == view.laps.driver_id)
+            .collect::<Vec<&Pitstop>>();
+        stops.sort_by_key(|p| p.pitstop_number);
+
+        for stop in stops {
+            let loss = |lap: i32| Some(view.lap_time(lap)? - pace?);
+            let in_lap_loss = loss(stop.lap_number);
+            let out_lap_loss = loss(stop.lap_number + 1);
+            vec.push(PitStopLoss {
+                driver_id: view.laps.driver_id.clone(),
+                stop: stop.pitstop_number,
+                lap: stop.lap_number,
+                duration: stop.duration.to_f64().unwrap_or_default(),
+                in_lap_loss,
+                out_lap_loss,
+                lap_loss: in_lap_loss.zip(out_lap_loss).map(|(i, o)| i + o),
+                position_before: view.position(stop.lap_number - 1),
+                position_after: view.position(stop.lap_number + 1),
+            });
+        }
+    }
+    vec
+}
+
+// undercuts and overcuts between pairs of drivers who were close before stopping.
+fn battles(views: &[DriverView]) -> Vec<PitBattle> {
+    let mut vec = Vec::new();
+    for a in views {
+        for b in views {
+            // a is the driver who stopped first
+            for stop_a in a.laps.pit_laps() {
+                for stop_b in b.laps.pit_laps() {
+                    if a.laps.driver_id == b.laps.driver_id
+                        || stop_b <= stop_a
+                        || stop_b - stop_a > MAX_STOP_WINDOW
+                    {
+                        continue;
+                    }
+                    // order and gap at the end of the lap before the first stop,
+                    // and after both drivers finished their out-laps
+                    let before = stop_a - 1;
+                    let after = stop_b + 1;
+                    let (gap_before, gap_after) = match (
+                        a.cumulative.get(&before),
+                        b.cumulative.get(&before),
+                        a.cumulative.get(&after),
+                        b.cumulative.get(&after),
+                    ) {
+                        // positive when a is ahead of b
+                        (Some(a0), Some(b0), Some(a1), Some(b1)) => (b0 - a0, b1 - a1),
+                        _ => continue,
+                    };
+                    if gap_before.abs() > CLOSE_GAP || gap_before.signum() == gap_after.signum() {
+                        continue;
+                    }
+                    let (kind, winner, loser) = if gap_after > 0.0 {
+                        ("undercut", a, b)
+                    } else {
+                        ("overcut", b, a)
+                    };
+                    vec.push(PitBattle {
+                        kind: kind.to_string(),
+                        winner: winner.laps.driver_id.clone(),
+                        loser: loser.laps.driver_id.clone(),
+                        first_stop_lap: stop_a,
+                        second_stop_lap: stop_b,
+                        gap_before: gap_before.abs(),
+                        gap_after: gap_after.abs(),
+                    });
+                }
+            }
+        }
+    }
+    vec.sort_by_key(|b| b.first_stop_lap);
+    vec
+}
+
+pub fn build(drivers: &[DriverLaps], pitstops: &[Pitstop]) -> PitAnalysisResponse {
+    let views = drivers
+        .iter()
+        .map(|d| DriverView {
+            laps: d,
+            cumulative: cumulative_times(d).into_iter().collect(),
+        })
+        .collect::<Vec<DriverView>>();
+
+    PitAnalysisResponse {
+        stops: stop_losses(&views, pitstops),
+        strategies: drivers
+            .iter()
+            .map(|d| DriverStrategy {
+                driver_id: d.driver_id.clone(),
+                code: d.code.clone(),
+                color: d.color.clone(),
+                stops: d.pit_laps().len(),
+                stop_laps: d.pit_laps(),
+                grid: d.grid,
+                finish: d.result_position,
+            })
+            .collect(),
+        battles: battles(&views),
+    }
+}
+
+pub async fn generate_response(race: &Race, conn: &mut PooledConnection) -> PitAnalysisResponse {
+    let drivers = laps::load(race, conn).await;
+    let pitstops = Pitstop::get(race, conn);
+    build(&drivers, &pitstops)
+}
@@ -0,0 +1,132 @@
+//! Points systems of the world championship since 1991.
+//!
+//! Only the rules which matter for the drivers' championship are modelled: points per
+//! finishing position in the race and the sprint, the bonus point for the fastest lap and
+//! counting only the best results of a driver.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// points awarded in one season. `race[0]` is the winner's points.
+#[derive(Debug, Clone)]
+pub struct PointsSystem {
+    pub race: Vec<i32>,
+    pub sprint: Vec<i32>,
+    pub fastest_lap: i32, // only scored by a driver finishing in the top 10
+    pub best_results: Option<usize>, // races which count for a driver, all if None
+}
+
+impl PointsSystem {
+    pub fn for_season(season: i32) -> Option<PointsSystem> {
+        let race = match season {
+            1991..=2002 => vec![10, 6, 4, 3, 2, 1],
+            2003..=2009 => vec![10, 8, 6, 5, 4, 3, 2, 1],
+            2010.. => vec![25, 18, 15, 12, 10, 8, 6, 4, 2, 1],
+            _ => return None,
+        };
+        let sprint = match season {
+            2021 => vec![3, 2, 1],
+            2022.. => vec![8, 7, 6, 5, 4, 3, 2, 1],
+            _ => Vec::new(),
+        };
+        let fastest_lap = match season {
+            2019..=2024 => 1,
+            _ => 0,
+        };
+        Some(PointsSystem {
+            race,
+            sprint,
+            fastest_lap,
+            best_results: None,
+        })
+    }
+
+    // race points of a historical system by the season it was introduced, e.g. "2010".
+    // sprints are not part of any of them.
+    pub fn named(name: &str) -> Option<PointsSystem> {
+        let season = match name {
+            "1991" | "2003" | "2010" => name.parse::<i32>().ok()?,
+            _ => return None,
+        };
+        Some(PointsSystem {
+            sprint: Vec::new(),
+            fastest_lap: 0,
+            ..PointsSystem::for_season(season)?
+        })
+    }
+
+    // points for finishing the race in `position` (1-based), without the fastest lap.
+    pub fn race_points(&self, position: i32) -> i32 {
+        points_at(&self.race, position)
+    }
+
+    pub fn sprint_points(&self, position: i32) -> i32 {
+        points_at(&self.sprint, position)
+    }
+
+    // the most a driver can score on a weekend.
+    pub fn max_points(&self, sprint: bool) -> i32 {
+        let sprint_points = if sprint { self.sprint_points(1) } else { 0 };
+        self.race_points(1) + self.fastest_lap + sprint_points
+    }
+}
+
+fn points_at(table: &[i32], position: i32) -> i32 {
+    usize::try_from(position - 1)
+        .ok()
+        .and_then(|i| table.get(i))
+        .copied()
+        .unwrap_or_default()
+}
+
+// total of a driver's race scores, keeping only the best results if the system drops some.
+pub fn total(system: &PointsSystem, scores: &[i32]) -> i32 {
+    match system.best_results {
+        Some(n) => {
+            let mut sorted = scores.to_vec();
+            sorted.sort_by(|a, b| b.cmp(a));
+            sorted.iter().take(n).sum()
+        }
+        None => scores.iter().sum(),
+    }
+}
+
+// count a classified finish in `position` (1-based), for countback.
+pub fn add_finish(finishes: &mut Vec<i32>, position: i32) {
+    let i = (position - 1) as usize;
+    if finishes.len() <= i {
+        finishes.resize(i + 1, 0);
+    }
+    finishes[i] += 1;
+}
+
+// entries in championship order with their positions: most points first, ties broken by
+// countback over `finishes`.
+pub fn rank<T>(
+    entries: &HashMap<String, T>,
+    points: impl Fn(&T) -> f64,
+    finishes: impl Fn(&T) -> &[i32],
+) -> Vec<(i32, &String, &T)> {
+    let mut order = entries.iter().collect::<Vec<(&String, &T)>>();
+    order.sort_by(|(_, a), (_, b)| {
+        points(b)
+            .total_cmp(&points(a))
+            .then_with(|| countback(finishes(a), finishes(b)))
+    });
+    order
+        .into_iter()
+        .enumerate()
+        .map(|(i, (id, entry))| (i as i32 + 1, id, entry))
+        .collect()
+}
+
+// championship order of two entries on equal points: most wins, then most second places
+// and so on. `finishes[i]` is the number of finishes in position i + 1.
+pub fn countback(a: &[i32], b: &[i32]) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let (x, y) = (a.get(i).unwrap_or(&0), b.get(i).unwrap_or(&0));
+        if x != y {
+            return y.cmp(x);
+        }
+    }
+    Ordering::Equal
+}
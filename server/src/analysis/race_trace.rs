@@ -0,0 +1,83 @@
+//! Race trace: cumulative race time per driver compared with the leader and with a
+//! constant reference pace.
+use crate::analysis::laps::{self, DriverLaps};
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::Race;
+use crate::models::{DriverTrace, RaceTraceResponse};
+
+// cumulative race time after each lap. stops at the first missing lap,
+// since the times after it cannot be compared anymore.
+pub fn cumulative_times(driver: &DriverLaps) -> Vec<(i32, f64)> {
+    let mut total = 0.0;
+    let mut vec = Vec::new();
+    for (expected, lap) in (1..).zip(&driver.laps) {
+        if lap.lap != expected {
+            break;
+        }
+        total += lap.time;
+        vec.push((lap.lap, total));
+    }
+    vec
+}
+
+/// builds the trace. the default reference pace is the winner's average lap time.
+pub fn build(drivers: &[DriverLaps], reference_pace: Option<f64>) -> RaceTraceResponse {
+    let cumulative = drivers.iter().map(cumulative_times).collect::<Vec<_>>();
+
+    let reference_pace = reference_pace.unwrap_or_else(|| {
+        drivers
+            .iter()
+            .zip(&cumulative)
+            .find(|(d, _)| d.result_position == 1)
+            .and_then(|(_, c)| c.last())
+            .map(|(lap, total)| total / *lap as f64)
+            .unwrap_or_default()
+    });
+
+    // leader's cumulative time on each lap, indexed by lap - 1
+    let mut leader: Vec<f64> = Vec::new();
+    for times in &cumulative {
+        for (lap, total) in times {
+            let i = (*lap - 1) as usize;
+            if i >= leader.len() {
+                leader.resize(i + 1, f64::INFINITY);
+            }
+            leader[i] = leader[i].min(*total);
+        }
+    }
+
+    let traces = drivers
+        .iter()
+        .zip(cumulative)
+        .map(|(driver, times)| DriverTrace {
+            driver_id: driver.driver_id.clone(),
+            code: driver.code.clone(),
+            color: driver.color.clone(),
+            laps: times.iter().map(|(lap, _)| *lap).collect(),
+            gap_to_leader: times
+                .iter()
+                .map(|(lap, total)| total - leader[(*lap - 1) as usize])
+                .collect(),
+            // positive when the driver is ahead of the reference
+            gap_to_reference: times
+                .iter()
+                .map(|(lap, total)| *lap as f64 * reference_pace - total)
+                .collect(),
+            pit_laps: driver.pit_laps(),
+        })
+        .collect();
+
+    RaceTraceResponse {
+        reference_pace,
+        drivers: traces,
+    }
+}
+
+pub async fn generate_response(
+    race: &Race,
+    reference_pace: Option<f64>,
+    conn: &mut PooledConnection,
+) -> RaceTraceResponse {
+    let drivers = laps::load(race, conn).await;
+    build(&drivers, reference_pace)
+}
@@ -0,0 +1,372 @@
+//! Elo rating of drivers across eras, from the stored race results.
+//!
+//! Each race is a set of pairwise matchups between the starters: finishing ahead of a
+//! driver is a win against them. A matchup against a teammate, the only one in equal
+//! machinery, weighs `TEAMMATE_WEIGHT` times as much. The K factor is spread over the
+//! matchups of a race, so a race moves a rating about as much as a single game would.
+//!
+//! A snapshot of every starter's rating is stored after each race, and the race is recorded
+//! in `rated_races`, even without starters. Races are processed in chronological order and
+//! only from the first unrated race, so newly fetched results are rated incrementally; if
+//! they predate rated races, those are replayed. Storing a race's results again marks it
+//! unrated, so refetched results are replayed too.
+//! Updates hold a transaction-level advisory lock, so concurrent requests rate each race
+//! once.
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{DriverRating, NewDriverRating, RaceResult};
+use crate::models::{PageResponse, RatingEntry, RatingHistoryResponse, RatingPoint};
+use crate::queries::RatingsQuery;
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text};
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+const INITIAL_RATING: f64 = 1500.0;
+const K: f64 = 32.0;
+const TEAMMATE_WEIGHT: f64 = 3.0;
+const UPDATE_LOCK: i64 = 0x7261_7469_6e67; // advisory lock key, "rating" in ASCII
+
+#[derive(Clone, Copy)]
+struct Rating {
+    rating: f64,
+    races: i32,
+}
+
+fn expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+// rating changes of the starters of one race, indexed like `results`.
+fn race_deltas(results: &[&RaceResult], ratings: &HashMap<String, Rating>) -> Vec<f64> {
+    let rating_of = |r: &RaceResult| {
+        ratings
+            .get(&r.driver_id)
+            .map(|r| r.rating)
+            .unwrap_or(INITIAL_RATING)
+    };
+    let mut deltas = vec![0.0; results.len()];
+    if results.len() < 2 {
+        return deltas;
+    }
+    let k = K / (results.len() - 1) as f64;
+    for i in 0..results.len() {
+        for j in i + 1..results.len() {
+            let (a, b) = (results[i], results[j]);
+            // race_results.position orders every entry, classified or not
+            let score = if a.position < b.position { 1.0 } else { 0.0 };
+            let weight = if a.constructor_id == b.constructor_id {
+                TEAMMATE_WEIGHT
+            } else {
+                1.0
+            };
+            let delta = k * weight * (score - expected(rating_of(a), rating_of(b)));
+            deltas[i] += delta;
+            deltas[j] -= delta;
+        }
+    }
+    deltas
+}
+
+#[derive(QueryableByName)]
+struct LatestRatingRow {
+    #[diesel(sql_type = Text)]
+    driver_id: String,
+    #[diesel(sql_type = Double)]
+    rating: f64,
+    #[diesel(sql_type = Integer)]
+    races: i32,
+}
+
+// rate the races which have results but are not rated yet. returns the number of races rated.
+pub fn update(conn: &mut PooledConnection) -> Result<usize, Error> {
+    use crate::db::schema::{race_results, races, rated_races};
+
+    // the common case, when every race is rated, takes neither the lock nor the race list
+    let unrated = diesel::select(diesel::dsl::exists(race_results::table.filter(
+        race_results::race_id.ne_all(rated_races::table.select(rated_races::race_id)),
+    )))
+    .get_result::<bool>(conn)?;
+    if !unrated {
+        return Ok(0);
+    }
+
+    conn.transaction(|conn| {
+        // a concurrent update waits here, and then sees the snapshots it committed
+        diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+            .bind::<BigInt, _>(UPDATE_LOCK)
+            .execute(conn)?;
+
+        let races = race_results::table
+            .inner_join(races::table)
+            .select((races::id, races::event_time))
+            .distinct()
+            .order((races::event_time.asc(), races::id.asc()))
+            .load::<(i32, SystemTime)>(conn)?;
+        let rated = DriverRating::rated_races(conn)?
+            .into_iter()
+            .collect::<HashSet<i32>>();
+
+        let first = match races.iter().position(|(id, _)| !rated.contains(id)) {
+            Some(i) => i,
+            None => return Ok(0),
+        };
+        let pending = races[first..]
+            .iter()
+            .map(|(id, _)| *id)
+            .collect::<Vec<i32>>();
+
+        // snapshots after the first unrated race depend on it, so they are replayed
+        DriverRating::delete_for_races(&pending, conn)?;
+
+        let mut ratings = diesel::sql_query(
+            "SELECT DISTINCT ON (dr.driver_id) dr.driver_id, dr.rating, dr.races
+            FROM driver_ratings dr
+            JOIN races r ON dr.race_id = r.id
+            ORDER BY dr.driver_id, r.event_time DESC, r.id DESC",
+        )
+        .load::<LatestRatingRow>(conn)?
+        .into_iter()
+        .map(|r| {
+            let rating = Rating {
+                rating: r.rating,
+                races: r.races,
+            };
+            (r.driver_id, rating)
+        })
+        .collect::<HashMap<String, Rating>>();
+
+        for race_id in &pending {
+            let results = race_results::table
+                .filter(race_results::race_id.eq(race_id))
+                .load::<RaceResult>(conn)?;
+            let starters = results
+                .iter()
+                .filter(|r| r.is_starter())
+                .collect::<Vec<&RaceResult>>();
+
+            let deltas = race_deltas(&starters, &ratings);
+            let mut snapshots = Vec::new();
+            for (result, delta) in starters.iter().zip(deltas) {
+                let entry = ratings.entry(result.driver_id.clone()).or_insert(Rating {
+                    rating: INITIAL_RATING,
+                    races: 0,
+                });
+                entry.rating += delta;
+                entry.races += 1;
+                snapshots.push(NewDriverRating {
+                    race_id: *race_id,
+                    driver_id: result.driver_id.clone(),
+                    rating: entry.rating,
+                    races: entry.races,
+                });
+            }
+            DriverRating::post(&snapshots, conn)?;
+            DriverRating::mark_rated(*race_id, conn)?;
+        }
+        Ok(pending.len())
+    })
+}
+
+#[derive(QueryableByName)]
+struct LeaderboardRow {
+    #[diesel(sql_type = Text)]
+    driver_id: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    code: Option<String>,
+    #[diesel(sql_type = Text)]
+    given_name: String,
+    #[diesel(sql_type = Text)]
+    family_name: String,
+    #[diesel(sql_type = Double)]
+    rating: f64,
+    #[diesel(sql_type = Double)]
+    peak_rating: f64,
+    #[diesel(sql_type = Integer)]
+    races: i32,
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Integer)]
+    round: i32,
+}
+
+// drivers by their latest rating, as of the end of `season` if it is set.
+pub fn leaderboard(
+    query: &RatingsQuery,
+    conn: &mut PooledConnection,
+) -> Result<PageResponse<RatingEntry>, Error> {
+    let rows = diesel::sql_query(
+        "SELECT * FROM (
+            SELECT DISTINCT ON (dr.driver_id) dr.driver_id, d.code, d.given_name, d.family_name,
+                dr.rating, MAX(dr.rating) OVER (PARTITION BY dr.driver_id) AS peak_rating,
+                dr.races, s.season, r.round
+            FROM driver_ratings dr
+            JOIN races r ON dr.race_id = r.id
+            JOIN seasons s ON r.season = s.id
+            JOIN drivers d ON dr.driver_id = d.id
+            WHERE ($1 IS NULL OR s.season <= $1)
+            ORDER BY dr.driver_id, r.event_time DESC, r.id DESC
+        ) latest
+        ORDER BY rating DESC, driver_id",
+    )
+    .bind::<Nullable<Integer>, _>(query.season)
+    .load::<LeaderboardRow>(conn)?;
+
+    let total = rows.len() as i64;
+    let items = rows
+        .into_iter()
+        .enumerate()
+        .skip(query.offset() as usize)
+        .take(query.limit() as usize)
+        .map(|(i, r)| RatingEntry {
+            rank: i as i32 + 1,
+            driver_id: r.driver_id,
+            code: r.code,
+            given_name: r.given_name,
+            family_name: r.family_name,
+            rating: r.rating,
+            peak_rating: r.peak_rating,
+            races: r.races,
+            last_season: r.season,
+            last_round: r.round,
+        })
+        .collect();
+
+    Ok(PageResponse {
+        total,
+        limit: query.limit(),
+        offset: query.offset(),
+        items,
+    })
+}
+
+#[derive(QueryableByName)]
+struct HistoryRow {
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Integer)]
+    round: i32,
+    #[diesel(sql_type = Text)]
+    race_name: String,
+    #[diesel(sql_type = Double)]
+    rating: f64,
+}
+
+// rating of the driver after each of their races. None if the driver has never been rated.
+pub fn history(
+    driver_id: &str,
+    conn: &mut PooledConnection,
+) -> Result<Option<RatingHistoryResponse>, Error> {
+    let rows = diesel::sql_query(
+        "SELECT s.season, r.round, r.race_name, dr.rating
+        FROM driver_ratings dr
+        JOIN races r ON dr.race_id = r.id
+        JOIN seasons s ON r.season = s.id
+        WHERE dr.driver_id = $1
+        ORDER BY r.event_time, r.id",
+    )
+    .bind::<Text, _>(driver_id)
+    .load::<HistoryRow>(conn)?;
+
+    let (rating, peak) = match rows.last() {
+        Some(last) => (
+            last.rating,
+            rows.iter().map(|r| r.rating).fold(f64::MIN, f64::max),
+        ),
+        None => return Ok(None),
+    };
+
+    let mut previous = INITIAL_RATING;
+    let history = rows
+        .into_iter()
+        .map(|r| {
+            let change = r.rating - previous;
+            previous = r.rating;
+            RatingPoint {
+                season: r.season,
+                round: r.round,
+                race_name: r.race_name,
+                rating: r.rating,
+                change,
+            }
+        })
+        .collect();
+
+    Ok(Some(RatingHistoryResponse {
+        driver_id: driver_id.to_string(),
+        rating,
+        peak_rating: peak,
+        history,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rated(driver_id: &str, rating: f64) -> (String, Rating) {
+        (driver_id.to_string(), Rating { rating, races: 1 })
+    }
+
+    #[test]
+    fn expected_score_is_even_between_equal_ratings() {
+        assert_eq!(expected(1500.0, 1500.0), 0.5);
+        // 400 points ahead is ten to one
+        assert!((expected(1900.0, 1500.0) - 10.0 / 11.0).abs() < 1e-9);
+        assert!((expected(1500.0, 1700.0) + expected(1700.0, 1500.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn k_factor_is_spread_over_the_matchups() {
+        let results = [
+            RaceResult::sample("max", "red_bull", 1, 25.0),
+            RaceResult::sample("lewis", "mercedes", 2, 18.0),
+            RaceResult::sample("charles", "ferrari", 3, 15.0),
+        ];
+        let starters = results.iter().collect::<Vec<&RaceResult>>();
+
+        let deltas = race_deltas(&starters, &HashMap::new());
+
+        assert_eq!(deltas, vec![16.0, 0.0, -16.0]);
+    }
+
+    #[test]
+    fn teammate_matchups_weigh_more() {
+        let results = [
+            RaceResult::sample("max", "red_bull", 1, 25.0),
+            RaceResult::sample("checo", "red_bull", 2, 18.0),
+        ];
+        let starters = results.iter().collect::<Vec<&RaceResult>>();
+
+        let deltas = race_deltas(&starters, &HashMap::new());
+
+        assert_eq!(
+            deltas,
+            vec![K * TEAMMATE_WEIGHT / 2.0, -K * TEAMMATE_WEIGHT / 2.0]
+        );
+    }
+
+    #[test]
+    fn deltas_are_zero_sum() {
+        let results = [
+            RaceResult::sample("charles", "ferrari", 1, 25.0),
+            RaceResult::sample("max", "red_bull", 2, 18.0),
+            RaceResult::sample("carlos", "ferrari", 3, 15.0),
+            RaceResult::sample("checo", "red_bull", 4, 12.0),
+        ];
+        let starters = results.iter().collect::<Vec<&RaceResult>>();
+        let ratings = HashMap::from([rated("max", 1700.0), rated("checo", 1550.0)]);
+
+        let deltas = race_deltas(&starters, &ratings);
+
+        assert!(deltas.iter().sum::<f64>().abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_lone_starter_is_not_rated() {
+        let results = [RaceResult::sample("max", "red_bull", 1, 25.0)];
+        let starters = results.iter().collect::<Vec<&RaceResult>>();
+
+        assert_eq!(race_deltas(&starters, &HashMap::new()), vec![0.0]);
+    }
+}
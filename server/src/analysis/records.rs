@@ -0,0 +1,334 @@
+//! All-time records and streaks over the stored race results.
+//!
+//! Everything is computed from one query over the results, optionally limited to a season
+//! or a constructor. With a constructor, only the results of its cars count, so a streak
+//! ends when the driver leaves the team. Streaks run over consecutive rounds of the
+//! calendar: a race the driver missed, or whose results are not stored, ends them too.
+//! Pole positions need qualifying data, which is
+//! not stored, and winning margins need the race times of the first two, so races where
+//! the runner-up was lapped are left out.
+use crate::db::connection::PooledConnection;
+use crate::models::{AgeRecord, CountRecord, MarginRecord, RaceRef, RecordsResponse, StreakRecord};
+use crate::queries::RecordsQuery;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::result::Error;
+use diesel::sql_types::{BigInt, Date, Integer, Nullable, Numeric, Text, Timestamp};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+const TOP: usize = 10;
+
+// season and constructor filter
+type CacheKey = (Option<i32>, Option<String>);
+// count of the stored results and sum of their versions, so that refetched results count too
+type Stamp = (i64, Option<i64>);
+
+/// records by filter, kept until results are stored or refetched.
+#[derive(Clone, Default)]
+pub struct RecordsCache {
+    entries: Arc<Mutex<HashMap<CacheKey, (Stamp, Value)>>>,
+}
+
+#[derive(QueryableByName)]
+struct ResultRow {
+    #[diesel(sql_type = Integer)]
+    race_id: i32,
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Integer)]
+    round: i32,
+    #[diesel(sql_type = Integer)]
+    season_rounds: i32,
+    #[diesel(sql_type = Text)]
+    race_name: String,
+    #[diesel(sql_type = Timestamp)]
+    event_time: NaiveDateTime,
+    #[diesel(sql_type = Text)]
+    driver_id: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    code: Option<String>,
+    #[diesel(sql_type = Date)]
+    date_of_birth: NaiveDate,
+    #[diesel(sql_type = Integer)]
+    position: i32,
+    #[diesel(sql_type = Text)]
+    position_text: String,
+    #[diesel(sql_type = Numeric)]
+    points: BigDecimal,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    time_millis: Option<i64>,
+}
+
+impl ResultRow {
+    fn is_win(&self) -> bool {
+        self.position == 1 && self.position_text == "1"
+    }
+
+    fn race(&self) -> RaceRef {
+        RaceRef {
+            season: self.season,
+            round: self.round,
+            race_name: self.race_name.clone(),
+        }
+    }
+
+    fn name(&self) -> String {
+        self.code.clone().unwrap_or("NA".to_string())
+    }
+
+    // whether the row is of the race right after the one of `prev` in the calendar.
+    fn follows(&self, prev: &ResultRow) -> bool {
+        if self.season == prev.season {
+            return self.round == prev.round + 1;
+        }
+        self.season == prev.season + 1 && self.round == 1 && prev.round == prev.season_rounds
+    }
+}
+
+fn load(query: &RecordsQuery, conn: &mut PooledConnection) -> Result<Vec<ResultRow>, Error> {
+    diesel::sql_query(
+        "SELECT r.id AS race_id, s.season, r.round,
+            (SELECT MAX(x.round) FROM races x WHERE x.season = r.season) AS season_rounds,
+            r.race_name, r.event_time,
+            rr.driver_id, d.code, d.date_of_birth, rr.position, rr.position_text, rr.points,
+            rr.time_millis
+        FROM race_results rr
+        JOIN races r ON rr.race_id = r.id
+        JOIN seasons s ON r.season = s.id
+        JOIN drivers d ON rr.driver_id = d.id
+        WHERE ($1 IS NULL OR s.season = $1) AND ($2 IS NULL OR rr.constructor_id = $2)
+        ORDER BY r.event_time, r.id, rr.position",
+    )
+    .bind::<Nullable<Integer>, _>(query.season)
+    .bind::<Nullable<Text>, _>(query.constructor.as_deref())
+    .load::<ResultRow>(conn)
+}
+
+fn most_wins(rows: &[ResultRow]) -> Vec<CountRecord> {
+    let mut map: HashMap<&str, CountRecord> = HashMap::new();
+    for row in rows.iter().filter(|r| r.is_win()) {
+        map.entry(&row.driver_id)
+            .or_insert_with(|| CountRecord {
+                driver_id: row.driver_id.clone(),
+                name: row.name(),
+                count: 0,
+            })
+            .count += 1;
+    }
+    let mut vec = map.into_values().collect::<Vec<CountRecord>>();
+    vec.sort_by(|a, b| b.count.cmp(&a.count).then(a.driver_id.cmp(&b.driver_id)));
+    vec.truncate(TOP);
+    vec
+}
+
+// longest runs of consecutive races of a driver where `hit` holds.
+fn streaks(rows: &[ResultRow], hit: impl Fn(&ResultRow) -> bool) -> Vec<StreakRecord> {
+    let mut by_driver: HashMap<&str, Vec<&ResultRow>> = HashMap::new();
+    for row in rows {
+        by_driver.entry(&row.driver_id).or_default().push(row);
+    }
+
+    let mut vec = Vec::new();
+    for results in by_driver.values() {
+        let mut best: Option<(usize, usize)> = None; // start and end index
+        let mut start = None;
+        for (i, row) in results.iter().enumerate() {
+            if i > 0 && !row.follows(results[i - 1]) {
+                start = None;
+            }
+            if !hit(row) {
+                start = None;
+                continue;
+            }
+            let s = *start.get_or_insert(i);
+            match best {
+                Some((bs, be)) if be - bs >= i - s => {}
+                _ => best = Some((s, i)),
+            }
+        }
+        if let Some((s, e)) = best {
+            vec.push(StreakRecord {
+                driver_id: results[s].driver_id.clone(),
+                name: results[s].name(),
+                length: e - s + 1,
+                from: results[s].race(),
+                to: results[e].race(),
+            });
+        }
+    }
+    vec.sort_by(|a, b| b.length.cmp(&a.length).then(a.driver_id.cmp(&b.driver_id)));
+    vec.truncate(TOP);
+    vec
+}
+
+// age of every winner on the day of their win, youngest first.
+fn winner_ages(rows: &[ResultRow]) -> Vec<AgeRecord> {
+    let mut vec = rows
+        .iter()
+        .filter(|r| r.is_win())
+        .map(|r| {
+            let days = (r.event_time.date() - r.date_of_birth).num_days();
+            AgeRecord {
+                driver_id: r.driver_id.clone(),
+                name: r.name(),
+                age_days: days,
+                age_years: days as f64 / 365.25,
+                race: r.race(),
+            }
+        })
+        .collect::<Vec<AgeRecord>>();
+    vec.sort_by_key(|a| a.age_days);
+    vec
+}
+
+// each driver once, at their youngest (or oldest) win.
+fn distinct_drivers(ages: impl Iterator<Item = AgeRecord>) -> Vec<AgeRecord> {
+    let mut seen = Vec::new();
+    ages.filter(|a| {
+        if seen.contains(&a.driver_id) {
+            return false;
+        }
+        seen.push(a.driver_id.clone());
+        true
+    })
+    .take(TOP)
+    .collect()
+}
+
+fn biggest_margins(rows: &[ResultRow]) -> Vec<MarginRecord> {
+    let mut by_race: HashMap<i32, Vec<&ResultRow>> = HashMap::new();
+    for row in rows {
+        by_race.entry(row.race_id).or_default().push(row);
+    }
+
+    let mut vec = by_race
+        .values()
+        .filter_map(|results| {
+            let winner = results.iter().find(|r| r.is_win())?;
+            let second = results
+                .iter()
+                .find(|r| r.position == 2 && r.position_text == "2")?;
+            let margin = second.time_millis? - winner.time_millis?;
+            Some(MarginRecord {
+                driver_id: winner.driver_id.clone(),
+                name: winner.name(),
+                runner_up: second.driver_id.clone(),
+                margin_seconds: margin as f64 / 1000.0,
+                race: winner.race(),
+            })
+        })
+        .collect::<Vec<MarginRecord>>();
+    vec.sort_by(|a, b| b.margin_seconds.total_cmp(&a.margin_seconds));
+    vec.truncate(TOP);
+    vec
+}
+
+pub fn generate_response(
+    query: &RecordsQuery,
+    conn: &mut PooledConnection,
+) -> Result<RecordsResponse, Error> {
+    let rows = load(query, conn)?;
+
+    let ages = winner_ages(&rows);
+    let races = rows
+        .iter()
+        .map(|r| r.race_id)
+        .collect::<HashSet<i32>>()
+        .len();
+
+    Ok(RecordsResponse {
+        season: query.season,
+        constructor: query.constructor.clone(),
+        races,
+        most_wins: most_wins(&rows),
+        win_streaks: streaks(&rows, |r| r.is_win()),
+        points_streaks: streaks(&rows, |r| r.points.to_f64().unwrap_or_default() > 0.0),
+        youngest_winners: distinct_drivers(ages.iter().cloned()),
+        oldest_winners: distinct_drivers(ages.into_iter().rev()),
+        biggest_margins: biggest_margins(&rows),
+    })
+}
+
+impl RecordsCache {
+    // the cached records, or freshly computed ones if results were stored since.
+    // the filters are not checked here; the handler only passes stored seasons and constructors.
+    pub fn get(&self, query: &RecordsQuery, conn: &mut PooledConnection) -> Result<Value, Error> {
+        use crate::db::schema::race_results;
+        use diesel::dsl::count_star;
+
+        let stored = race_results::table
+            .select((
+                count_star(),
+                diesel::dsl::sum(race_results::results_version),
+            ))
+            .get_result::<Stamp>(conn)?;
+        let key = (query.season, query.constructor.clone());
+        if let Some((stamp, value)) = self.entries.lock().unwrap().get(&key) {
+            if *stamp == stored {
+                return Ok(value.clone());
+            }
+        }
+
+        let value = serde_json::to_value(generate_response(query, conn)?).unwrap();
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (stored, value.clone()));
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win(season: i32, round: i32) -> ResultRow {
+        ResultRow {
+            race_id: season * 100 + round,
+            season,
+            round,
+            season_rounds: 22,
+            race_name: String::new(),
+            event_time: NaiveDateTime::default(),
+            driver_id: "max".to_string(),
+            code: None,
+            date_of_birth: NaiveDate::default(),
+            position: 1,
+            position_text: "1".to_string(),
+            points: BigDecimal::from(25),
+            time_millis: None,
+        }
+    }
+
+    fn longest(rows: &[ResultRow]) -> usize {
+        streaks(rows, |r| r.is_win())[0].length
+    }
+
+    #[test]
+    fn streaks_run_over_the_end_of_a_season() {
+        assert_eq!(longest(&[win(2022, 21), win(2022, 22), win(2023, 1)]), 3);
+    }
+
+    #[test]
+    fn streaks_end_at_a_missing_round() {
+        // round 3 is not stored, or the driver was not in the team's car
+        assert_eq!(
+            longest(&[
+                win(2023, 1),
+                win(2023, 2),
+                win(2023, 4),
+                win(2023, 5),
+                win(2023, 6)
+            ]),
+            3
+        );
+    }
+
+    #[test]
+    fn streaks_end_at_a_missing_season() {
+        assert_eq!(longest(&[win(2021, 22), win(2023, 1)]), 1);
+    }
+}
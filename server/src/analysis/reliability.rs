@@ -0,0 +1,141 @@
+//! Reliability of each constructor over a season, from the `status` of the race results.
+//!
+//! Statuses are free text in Ergast: "Finished" and "+n Laps" are finishes, a few describe
+//! incidents, and the rest of the retirements are put down to the car. Disqualifications,
+//! withdrawals and non-qualifications count as neither.
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Constructor, Race, RaceResult, Season};
+use crate::models::{ConstructorReliability, ReliabilityResponse, StatusCount};
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+const TOP_STATUSES: usize = 5;
+
+const ACCIDENT_STATUSES: [&str; 8] = [
+    "Accident",
+    "Collision",
+    "Collision damage",
+    "Spun off",
+    "Fatal accident",
+    "Puncture",
+    "Damage",
+    "Debris",
+];
+
+const OTHER_STATUSES: [&str; 10] = [
+    "Disqualified",
+    "Excluded",
+    "Withdrew",
+    "Withdrawn",
+    "Did not qualify",
+    "Did not prequalify",
+    "Not classified",
+    "Retired",
+    "Illness",
+    "Injured",
+];
+
+enum Outcome {
+    Finished,
+    Accident,
+    Mechanical,
+    Other,
+}
+
+fn classify(status: &str) -> Outcome {
+    if status == "Finished" || status.starts_with('+') {
+        Outcome::Finished
+    } else if ACCIDENT_STATUSES.contains(&status) {
+        Outcome::Accident
+    } else if OTHER_STATUSES.contains(&status) {
+        Outcome::Other
+    } else {
+        Outcome::Mechanical
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    starts: i32,
+    classified: i32,
+    mechanical: i32,
+    accidents: i32,
+    other: i32,
+    laps: i32,
+    distance: i32,
+    statuses: HashMap<String, i32>,
+}
+
+pub async fn generate_response(
+    season: &Season,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> ReliabilityResponse {
+    let races = Race::get_completed_in_season(season, now, conn).await;
+
+    let mut map: HashMap<String, Tally> = HashMap::new();
+    for race in &races {
+        RaceResult::fetch_if_missing(race, conn).await;
+        let results = RaceResult::get(race, conn);
+        // the race distance is the laps completed by the winner
+        let distance = results.iter().map(|r| r.laps).max().unwrap_or_default();
+
+        for result in results.iter().filter(|r| r.is_starter()) {
+            let tally = map.entry(result.constructor_id.clone()).or_default();
+            tally.starts += 1;
+            tally.laps += result.laps;
+            tally.distance += distance;
+            if result.is_classified() {
+                tally.classified += 1;
+            }
+            match classify(&result.status) {
+                Outcome::Finished => continue,
+                Outcome::Accident => tally.accidents += 1,
+                Outcome::Mechanical => tally.mechanical += 1,
+                Outcome::Other => tally.other += 1,
+            }
+            *tally.statuses.entry(result.status.clone()).or_default() += 1;
+        }
+    }
+
+    let mut constructors = map
+        .into_iter()
+        .map(|(id, t)| {
+            let constructor = Constructor::get_by_id(&id, conn);
+            let mut failures = t
+                .statuses
+                .into_iter()
+                .map(|(status, count)| StatusCount { status, count })
+                .collect::<Vec<StatusCount>>();
+            failures.sort_by(|a, b| b.count.cmp(&a.count).then(a.status.cmp(&b.status)));
+            failures.truncate(TOP_STATUSES);
+
+            ConstructorReliability {
+                constructor_id: id,
+                name: constructor.name.clone(),
+                color: constructor.team_color().to_string(),
+                starts: t.starts,
+                classified: t.classified,
+                finish_rate: t.classified as f64 / t.starts as f64,
+                retirements: t.mechanical + t.accidents + t.other,
+                mechanical: t.mechanical,
+                accidents: t.accidents,
+                other: t.other,
+                laps_completed: t.laps,
+                laps_ratio: if t.distance > 0 {
+                    t.laps as f64 / t.distance as f64
+                } else {
+                    0.0
+                },
+                failures,
+            }
+        })
+        .collect::<Vec<ConstructorReliability>>();
+    constructors.sort_by(|a, b| b.finish_rate.total_cmp(&a.finish_rate));
+
+    ReliabilityResponse {
+        season: season.season,
+        races: races.len(),
+        constructors,
+    }
+}
@@ -0,0 +1,284 @@
+//! What-if standings: a season's race results scored under another points system.
+//!
+//! The historical systems predate sprints, so sprints are re-scored with the season's own
+//! sprint points, and never dropped. Constructors score both of their drivers' points in
+//! every race; dropping results applies to drivers only.
+use crate::analysis::points::{self, PointsSystem};
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Constructor, Driver, Race, RaceResult, Season, SprintResult};
+use crate::models::{RescoreResponse, RescoredEntry};
+use bigdecimal::ToPrimitive;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+#[derive(Default)]
+struct Tally {
+    scores: Vec<i32>, // per race, for dropping results
+    sprint_score: i32,
+    real_points: f64,
+    finishes: Vec<i32>, // count of each classified position, for countback
+}
+
+impl Tally {
+    fn add(&mut self, result: &RaceResult, system: &PointsSystem) {
+        let classified = result.is_classified();
+        let mut score = 0;
+        if classified {
+            score += system.race_points(result.position);
+            if result.fastest_lap_rank == Some(1) && result.position <= 10 {
+                score += system.fastest_lap;
+            }
+            points::add_finish(&mut self.finishes, result.position);
+        }
+        self.scores.push(score);
+        self.real_points += result.points.to_f64().unwrap_or_default();
+    }
+
+    fn add_sprint(&mut self, result: &SprintResult, system: &PointsSystem) {
+        if result.is_classified() {
+            self.sprint_score += system.sprint_points(result.position);
+        }
+        self.real_points += result.points.to_f64().unwrap_or_default();
+    }
+
+    fn points(&self, system: &PointsSystem) -> i32 {
+        points::total(system, &self.scores) + self.sprint_score
+    }
+}
+
+// positions of the entries by points, ties broken by countback.
+fn positions(
+    tallies: &HashMap<String, Tally>,
+    points: impl Fn(&Tally) -> f64,
+) -> HashMap<&str, i32> {
+    points::rank(tallies, points, |t| &t.finishes)
+        .into_iter()
+        .map(|(position, id, _)| (id.as_str(), position))
+        .collect()
+}
+
+fn table(
+    tallies: &HashMap<String, Tally>,
+    system: &PointsSystem,
+    mut describe: impl FnMut(&str) -> (String, String),
+) -> Vec<RescoredEntry> {
+    let rescored = positions(tallies, |t| t.points(system) as f64);
+    let real = positions(tallies, |t| t.real_points);
+
+    let mut vec = tallies
+        .iter()
+        .map(|(id, tally)| {
+            let (name, color) = describe(id);
+            let points = tally.points(system);
+            let position = rescored[id.as_str()];
+            let real_position = real[id.as_str()];
+            RescoredEntry {
+                id: id.clone(),
+                name,
+                color,
+                points,
+                dropped_points: tally.scores.iter().sum::<i32>()
+                    - points::total(system, &tally.scores),
+                position,
+                real_points: tally.real_points,
+                real_position,
+                position_change: real_position - position,
+            }
+        })
+        .collect::<Vec<RescoredEntry>>();
+    vec.sort_by_key(|e| e.position);
+    vec
+}
+
+pub async fn generate_response(
+    season: &Season,
+    system_name: &str,
+    fastest_lap: bool,
+    best_results: Option<usize>,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<RescoreResponse> {
+    let system = PointsSystem {
+        sprint: PointsSystem::for_season(season.season)
+            .map(|s| s.sprint)
+            .unwrap_or_default(),
+        fastest_lap: if fastest_lap { 1 } else { 0 },
+        best_results,
+        ..PointsSystem::named(system_name)?
+    };
+
+    let races = Race::get_completed_in_season(season, now, conn).await;
+    let mut drivers: HashMap<String, Tally> = HashMap::new();
+    let mut constructors: HashMap<String, Tally> = HashMap::new();
+    let mut latest_team: HashMap<String, String> = HashMap::new();
+    for race in &races {
+        RaceResult::fetch_if_missing(race, conn).await;
+        for result in RaceResult::get(race, conn) {
+            drivers
+                .entry(result.driver_id.clone())
+                .or_default()
+                .add(&result, &system);
+            // constructors do not drop results, so their scores need not be per race
+            constructors
+                .entry(result.constructor_id.clone())
+                .or_default()
+                .add(&result, &system);
+            latest_team.insert(result.driver_id.clone(), result.constructor_id.clone());
+        }
+
+        SprintResult::fetch_if_missing(race, conn).await;
+        if !race.has_sprint() {
+            continue;
+        }
+        for result in SprintResult::get(race, conn) {
+            drivers
+                .entry(result.driver_id.clone())
+                .or_default()
+                .add_sprint(&result, &system);
+            constructors
+                .entry(result.constructor_id.clone())
+                .or_default()
+                .add_sprint(&result, &system);
+        }
+    }
+
+    let driver_table = table(&drivers, &system, |id| {
+        let driver = Driver::get_by_id(id, conn);
+        let color = latest_team
+            .get(id)
+            .map(|c| Constructor::get_by_id(c, conn).team_color().to_string())
+            .unwrap_or_default();
+        (driver.code.unwrap_or("NA".to_string()), color)
+    });
+    // dropping results is a drivers' championship rule
+    let constructor_system = PointsSystem {
+        best_results: None,
+        ..system.clone()
+    };
+    let constructor_table = table(&constructors, &constructor_system, |id| {
+        let constructor = Constructor::get_by_id(id, conn);
+        (
+            constructor.name.clone(),
+            constructor.team_color().to_string(),
+        )
+    });
+
+    Some(RescoreResponse {
+        season: season.season,
+        system: system_name.to_string(),
+        fastest_lap,
+        best_results,
+        races: races.len(),
+        drivers: driver_table,
+        constructors: constructor_table,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tallies(system: &PointsSystem, results: &[RaceResult]) -> HashMap<String, Tally> {
+        let mut map: HashMap<String, Tally> = HashMap::new();
+        for result in results {
+            map.entry(result.driver_id.clone())
+                .or_default()
+                .add(result, system);
+        }
+        map
+    }
+
+    fn rescore(system: &PointsSystem, results: &[RaceResult]) -> Vec<RescoredEntry> {
+        table(&tallies(system, results), system, |id| {
+            (id.to_uppercase(), String::new())
+        })
+    }
+
+    #[test]
+    fn fastest_lap_scores_only_in_the_top_ten() {
+        let system = PointsSystem {
+            fastest_lap: 1,
+            ..PointsSystem::named("2010").unwrap()
+        };
+        let mut winner = RaceResult::sample("max", "red_bull", 1, 25.0);
+        winner.fastest_lap_rank = Some(1);
+        let mut eleventh = RaceResult::sample("sar", "williams", 11, 0.0);
+        eleventh.fastest_lap_rank = Some(1);
+
+        let table = rescore(&system, &[winner, eleventh]);
+        assert_eq!(table[0].points, 26);
+        assert_eq!(table[1].points, 0);
+    }
+
+    #[test]
+    fn unclassified_results_do_not_score() {
+        let system = PointsSystem::named("1991").unwrap();
+        let mut retired = RaceResult::sample("ham", "mercedes", 3, 0.0);
+        retired.position_text = "R".to_string();
+
+        let table = rescore(&system, &[retired]);
+        assert_eq!(table[0].points, 0);
+    }
+
+    #[test]
+    fn ties_are_broken_by_countback() {
+        let system = PointsSystem::named("1991").unwrap();
+        let results = [
+            RaceResult::sample("lec", "ferrari", 2, 18.0),
+            RaceResult::sample("lec", "ferrari", 3, 15.0),
+            RaceResult::sample("max", "red_bull", 1, 25.0),
+            RaceResult::sample("max", "red_bull", 7, 6.0),
+        ];
+        // 6 + 4 against 10 + 0 under the 1991 system: the win decides
+        let table = rescore(&system, &results);
+        assert_eq!(table[0].id, "max");
+        assert_eq!(table[0].points, 10);
+        assert_eq!(table[1].points, 10);
+        // 33 real points against 31
+        assert_eq!(table[0].real_position, 2);
+        assert_eq!(table[0].position_change, 1);
+    }
+
+    #[test]
+    fn sprints_score_but_are_never_dropped() {
+        let system = PointsSystem {
+            best_results: Some(1),
+            ..PointsSystem::for_season(2022).unwrap()
+        };
+        let sprint = SprintResult {
+            driver_id: "max".to_string(),
+            constructor_id: "red_bull".to_string(),
+            position: 2,
+            position_text: "2".to_string(),
+            points: 7.into(),
+        };
+        let mut map = tallies(
+            &system,
+            &[
+                RaceResult::sample("max", "red_bull", 1, 25.0),
+                RaceResult::sample("max", "red_bull", 3, 15.0),
+            ],
+        );
+        map.get_mut("max").unwrap().add_sprint(&sprint, &system);
+
+        let table = table(&map, &system, |id| (id.to_string(), String::new()));
+        assert_eq!(table[0].points, 32);
+        assert_eq!(table[0].dropped_points, 15);
+        assert_eq!(table[0].real_points, 47.0);
+    }
+
+    #[test]
+    fn only_the_best_results_count() {
+        let system = PointsSystem {
+            best_results: Some(1),
+            ..PointsSystem::named("2003").unwrap()
+        };
+        let results = [
+            RaceResult::sample("alo", "renault", 1, 10.0),
+            RaceResult::sample("alo", "renault", 2, 8.0),
+        ];
+        let table = rescore(&system, &results);
+        assert_eq!(table[0].points, 10);
+        assert_eq!(table[0].dropped_points, 8);
+    }
+}
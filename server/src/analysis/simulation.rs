@@ -0,0 +1,290 @@
+//! Monte Carlo estimate of the championship from the current standings, computed from the
+//! stored results for drivers and constructors alike.
+//!
+//! Every remaining race and sprint is simulated by drawing, for each driver, one of their
+//! grand prix results of the season so far. Drawn positions are ordered with a random
+//! tie-break to give the simulated finishing order, and a drawn retirement scores nothing.
+//! The fastest lap point, where it exists, goes to a random driver in the top 10.
+//! Constructors keep the points scored for them so far, whichever driver scored them, and
+//! score the simulated points of the drivers who race for them now.
+use crate::analysis::points::PointsSystem;
+use crate::analysis::standings;
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Race, RaceResult, Season};
+use crate::models::{SimulatedEntry, SimulationResponse};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_ITERATIONS: usize = 10000;
+pub const MAX_ITERATIONS: usize = 100000;
+
+// SplitMix64. small, seedable and good enough for a simulation.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+}
+
+// a driver, in the order of the computed standings.
+struct Contender {
+    constructor_id: String, // of the latest race, empty without any grand prix result
+    points: f64,
+    history: Vec<Option<i32>>, // classified position of each race, None for a retirement
+}
+
+// simulated finishing order of a race: indices into `contenders` of the classified drivers.
+fn simulate_race(contenders: &[Contender], rng: &mut Rng) -> Vec<usize> {
+    let mut scores = contenders
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| {
+            if c.history.is_empty() {
+                return None;
+            }
+            let drawn = c.history[rng.below(c.history.len())]?;
+            Some((i, drawn as f64 + rng.next_f64()))
+        })
+        .collect::<Vec<(usize, f64)>>();
+    scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+    scores.into_iter().map(|(i, _)| i).collect()
+}
+
+// final positions of one simulated season, indexed like `points`. ties keep the order of
+// the current standings.
+fn final_positions(points: &[f64]) -> Vec<usize> {
+    let mut order = (0..points.len()).collect::<Vec<usize>>();
+    order.sort_by(|&a, &b| points[b].total_cmp(&points[a]));
+    let mut positions = vec![0; points.len()];
+    for (position, i) in order.into_iter().enumerate() {
+        positions[i] = position;
+    }
+    positions
+}
+
+struct Outcome {
+    points: Vec<f64>,           // mean final points
+    positions: Vec<Vec<usize>>, // count of each final position
+}
+
+impl Outcome {
+    fn new(n: usize) -> Self {
+        Outcome {
+            points: vec![0.0; n],
+            positions: vec![vec![0; n]; n],
+        }
+    }
+
+    fn add(&mut self, points: &[f64]) {
+        for (i, position) in final_positions(points).into_iter().enumerate() {
+            self.points[i] += points[i];
+            self.positions[i][position] += 1;
+        }
+    }
+
+    fn entry(
+        &self,
+        i: usize,
+        iterations: usize,
+        id: String,
+        name: String,
+        color: String,
+    ) -> SimulatedEntry {
+        let distribution = self.positions[i]
+            .iter()
+            .map(|&c| c as f64 / iterations as f64)
+            .collect::<Vec<f64>>();
+        SimulatedEntry {
+            id,
+            name,
+            color,
+            expected_points: self.points[i] / iterations as f64,
+            title_probability: distribution[0],
+            expected_position: distribution
+                .iter()
+                .enumerate()
+                .map(|(p, prob)| (p + 1) as f64 * prob)
+                .sum(),
+            position_distribution: distribution,
+        }
+    }
+}
+
+pub async fn generate_response(
+    season: &Season,
+    iterations: usize,
+    seed: Option<u64>,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<SimulationResponse> {
+    let system = PointsSystem::for_season(season.season)?;
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default()
+    });
+    let mut rng = Rng(seed);
+
+    let completed = Race::get_completed_in_season(season, now, conn).await;
+    let remaining = Race::get_remaining_in_season(season, now, conn).await;
+
+    // fetches the missing results of the completed rounds, so they are loaded from here on
+    let (drivers, constructors) = match completed.last() {
+        Some(race) => {
+            let computed = standings::generate_response(season, race, now, conn).await?;
+            (computed.drivers, computed.constructors)
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let mut history: HashMap<String, Vec<Option<i32>>> = HashMap::new();
+    let mut latest_team: HashMap<String, String> = HashMap::new();
+    for race in &completed {
+        for result in RaceResult::get(race, conn) {
+            let position = result.is_classified().then_some(result.position);
+            latest_team.insert(result.driver_id.clone(), result.constructor_id);
+            history.entry(result.driver_id).or_default().push(position);
+        }
+    }
+
+    let contenders = drivers
+        .iter()
+        .map(|s| Contender {
+            constructor_id: latest_team.remove(&s.id).unwrap_or_default(),
+            points: s.points,
+            history: history.remove(&s.id).unwrap_or_default(),
+        })
+        .collect::<Vec<Contender>>();
+
+    // points scored for each constructor so far, by the result's constructor
+    let team_base = constructors.iter().map(|s| s.points).collect::<Vec<f64>>();
+    let team_of = contenders
+        .iter()
+        .map(|c| constructors.iter().position(|t| t.id == c.constructor_id))
+        .collect::<Vec<Option<usize>>>();
+
+    let mut driver_outcome = Outcome::new(contenders.len());
+    let mut team_outcome = Outcome::new(constructors.len());
+    for _ in 0..iterations {
+        let mut gained = vec![0; contenders.len()];
+        for race in &remaining {
+            let order = simulate_race(&contenders, &mut rng);
+            for (p, &i) in order.iter().enumerate() {
+                gained[i] += system.race_points(p as i32 + 1);
+            }
+            if system.fastest_lap > 0 && !order.is_empty() {
+                let top = order.len().min(10);
+                gained[order[rng.below(top)]] += system.fastest_lap;
+            }
+            if race.has_sprint() {
+                let order = simulate_race(&contenders, &mut rng);
+                for (p, &i) in order.iter().enumerate() {
+                    gained[i] += system.sprint_points(p as i32 + 1);
+                }
+            }
+        }
+
+        let points = contenders
+            .iter()
+            .zip(&gained)
+            .map(|(c, &g)| c.points + g as f64)
+            .collect::<Vec<f64>>();
+        let mut team_points = team_base.clone();
+        for (i, g) in gained.iter().enumerate() {
+            if let Some(team) = team_of[i] {
+                team_points[team] += *g as f64;
+            }
+        }
+        driver_outcome.add(&points);
+        team_outcome.add(&team_points);
+    }
+
+    let mut drivers = drivers
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| driver_outcome.entry(i, iterations, s.id, s.name, s.color))
+        .collect::<Vec<SimulatedEntry>>();
+    let mut constructors = constructors
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| team_outcome.entry(i, iterations, s.id, s.name, s.color))
+        .collect::<Vec<SimulatedEntry>>();
+    drivers.sort_by(|a, b| b.title_probability.total_cmp(&a.title_probability));
+    constructors.sort_by(|a, b| b.title_probability.total_cmp(&a.title_probability));
+
+    Some(SimulationResponse {
+        season: season.season,
+        iterations,
+        seed,
+        remaining_rounds: remaining.len(),
+        drivers,
+        constructors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contender(history: Vec<Option<i32>>) -> Contender {
+        Contender {
+            constructor_id: "ferrari".to_string(),
+            points: 0.0,
+            history,
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_draws() {
+        let mut a = Rng(42);
+        let mut b = Rng(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        let x = Rng(7).next_f64();
+        assert!((0.0..1.0).contains(&x));
+    }
+
+    #[test]
+    fn drawn_retirements_and_drivers_without_results_are_not_classified() {
+        let contenders = [
+            contender(vec![Some(2)]),
+            contender(vec![None]),
+            contender(Vec::new()),
+            contender(vec![Some(1)]),
+        ];
+        let order = simulate_race(&contenders, &mut Rng(1));
+        assert_eq!(order, vec![3, 0]);
+    }
+
+    #[test]
+    fn ties_keep_the_current_order() {
+        assert_eq!(final_positions(&[10.0, 25.0, 10.0]), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn outcome_averages_over_the_iterations() {
+        let mut outcome = Outcome::new(2);
+        outcome.add(&[10.0, 0.0]);
+        outcome.add(&[0.0, 20.0]);
+        let entry = outcome.entry(0, 2, "lec".to_string(), String::new(), String::new());
+        assert_eq!(entry.expected_points, 5.0);
+        assert_eq!(entry.title_probability, 0.5);
+        assert_eq!(entry.expected_position, 1.5);
+    }
+}
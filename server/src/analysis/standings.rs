@@ -0,0 +1,361 @@
+//! Driver and constructor standings computed from the stored race and sprint results,
+//! instead of fetching them from Ergast API round by round.
+//!
+//! Points are summed as they were awarded, so fastest lap and half points are included.
+//! Ties are broken by countback over the grand prix results: most wins, then most second
+//! places and so on. Sprint finishes do not count for it. Only seasons with a known points
+//! system are computed: before 1991 only a driver's best results counted, by rules which
+//! are not modelled, so a plain sum would not be the table.
+use crate::analysis::points::{self, PointsSystem};
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::{Constructor, Driver, Race, RaceResult, Season, SprintResult, Standing};
+use crate::models::{
+    ChartResponse, ComputedStanding, ComputedStandingsResponse, StandingMismatch,
+    StandingsCheckResponse,
+};
+use bigdecimal::ToPrimitive;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+#[derive(Default)]
+struct Tally {
+    points: f64,
+    finishes: Vec<i32>, // count of each classified grand prix position
+}
+
+impl Tally {
+    fn add_race(&mut self, result: &RaceResult) {
+        self.points += result.points.to_f64().unwrap_or_default();
+        if result.is_classified() {
+            points::add_finish(&mut self.finishes, result.position);
+        }
+    }
+
+    fn add_sprint(&mut self, result: &SprintResult) {
+        self.points += result.points.to_f64().unwrap_or_default();
+    }
+
+    fn wins(&self) -> i32 {
+        self.finishes.first().copied().unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct Tallies {
+    drivers: HashMap<String, Tally>,
+    constructors: HashMap<String, Tally>,
+    latest_team: HashMap<String, String>, // driver to constructor
+}
+
+impl Tallies {
+    fn add_race(&mut self, result: &RaceResult) {
+        self.drivers
+            .entry(result.driver_id.clone())
+            .or_default()
+            .add_race(result);
+        self.constructors
+            .entry(result.constructor_id.clone())
+            .or_default()
+            .add_race(result);
+        self.latest_team
+            .insert(result.driver_id.clone(), result.constructor_id.clone());
+    }
+
+    fn add_sprint(&mut self, result: &SprintResult) {
+        self.drivers
+            .entry(result.driver_id.clone())
+            .or_default()
+            .add_sprint(result);
+        self.constructors
+            .entry(result.constructor_id.clone())
+            .or_default()
+            .add_sprint(result);
+    }
+}
+
+// the completed rounds up to `round`, with their race and sprint results fetched from
+// Ergast API if missing. None if the season has no known points system.
+pub async fn completed_races(
+    season: &Season,
+    round: i32,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<Vec<Race>> {
+    PointsSystem::for_season(season.season)?;
+    let mut races = Race::get_completed_in_season(season, now, conn).await;
+    races.retain(|r| r.round() <= round);
+    for race in &races {
+        RaceResult::fetch_if_missing(race, conn).await;
+        SprintResult::fetch_if_missing(race, conn).await;
+    }
+    Some(races)
+}
+
+async fn tally(
+    season: &Season,
+    round: i32,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<Tallies> {
+    let mut tallies = Tallies::default();
+    for race in completed_races(season, round, now, conn).await? {
+        for result in RaceResult::get(&race, conn) {
+            tallies.add_race(&result);
+        }
+        if race.has_sprint() {
+            for result in SprintResult::get(&race, conn) {
+                tallies.add_sprint(&result);
+            }
+        }
+    }
+    Some(tallies)
+}
+
+// championship position, id and points of the drivers in results which are loaded
+// already, e.g. those of `completed_races`.
+pub fn rank_drivers<'a>(
+    results: impl IntoIterator<Item = &'a RaceResult>,
+    sprints: &[SprintResult],
+) -> Vec<(i32, String, f64)> {
+    let mut tallies = Tallies::default();
+    results.into_iter().for_each(|r| tallies.add_race(r));
+    sprints.iter().for_each(|s| tallies.add_sprint(s));
+    rank(&tallies.drivers)
+        .into_iter()
+        .map(|(position, id, tally)| (position, id.clone(), tally.points))
+        .collect()
+}
+
+// entries in championship order with their positions.
+fn rank(tallies: &HashMap<String, Tally>) -> Vec<(i32, &String, &Tally)> {
+    points::rank(tallies, |t| t.points, |t| &t.finishes)
+}
+
+fn driver_standings(tallies: &Tallies, conn: &mut PooledConnection) -> Vec<ComputedStanding> {
+    rank(&tallies.drivers)
+        .into_iter()
+        .map(|(position, id, tally)| {
+            let driver = Driver::get_by_id(id, conn);
+            let color = tallies
+                .latest_team
+                .get(id)
+                .map(|c| Constructor::get_by_id(c, conn).team_color().to_string())
+                .unwrap_or_default();
+            ComputedStanding {
+                id: id.clone(),
+                name: driver.code.unwrap_or("NA".to_string()),
+                color,
+                position,
+                points: tally.points,
+                wins: tally.wins(),
+            }
+        })
+        .collect()
+}
+
+fn constructor_standings(tallies: &Tallies, conn: &mut PooledConnection) -> Vec<ComputedStanding> {
+    rank(&tallies.constructors)
+        .into_iter()
+        .map(|(position, id, tally)| {
+            let constructor = Constructor::get_by_id(id, conn);
+            ComputedStanding {
+                id: id.clone(),
+                name: constructor.name.clone(),
+                color: constructor.team_color().to_string(),
+                position,
+                points: tally.points,
+                wins: tally.wins(),
+            }
+        })
+        .collect()
+}
+
+// the constructor standings after `round`, e.g. i32::MAX for the latest completed round.
+pub async fn constructors_after(
+    season: &Season,
+    round: i32,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<Vec<ComputedStanding>> {
+    let tallies = tally(season, round, now, conn).await?;
+    Some(constructor_standings(&tallies, conn))
+}
+
+pub async fn generate_response(
+    season: &Season,
+    race: &Race,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<ComputedStandingsResponse> {
+    let tallies = tally(season, race.round(), now, conn).await?;
+    Some(ComputedStandingsResponse {
+        season: season.season,
+        round: race.round(),
+        drivers: driver_standings(&tallies, conn),
+        constructors: constructor_standings(&tallies, conn),
+    })
+}
+
+// the driver standings in the shape of `Standing::generate_response`.
+pub async fn generate_chart(
+    season: &Season,
+    race: &Race,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<ChartResponse<String, f64>> {
+    let tallies = tally(season, race.round(), now, conn).await?;
+    let standings = driver_standings(&tallies, conn);
+    Some(ChartResponse {
+        x: standings.iter().map(|s| s.name.clone()).collect(),
+        y: standings.iter().map(|s| s.points).collect(),
+        color: Some(standings.into_iter().map(|s| s.color).collect()),
+    })
+}
+
+// differences between the computed driver standings and the ones from Ergast API.
+pub async fn check(
+    season: &Season,
+    race: &Race,
+    now: SystemTime,
+    conn: &mut PooledConnection,
+) -> Option<StandingsCheckResponse> {
+    let tallies = tally(season, race.round(), now, conn).await?;
+    if !Standing::is_exist(race, conn) {
+        println!("Standing data is not in the database. Fetch from Ergast API.");
+        Standing::post(race, conn).await;
+    }
+    let upstream = Standing::get(race, conn)
+        .into_iter()
+        .map(|s| (s.driver_id.clone(), s))
+        .collect::<HashMap<String, Standing>>();
+
+    let local = rank(&tallies.drivers);
+
+    let mut mismatches = Vec::new();
+    for (position, id, tally) in &local {
+        let standing = upstream.get(id.as_str());
+        // stored upstream points are whole numbers, so half points are not a mismatch
+        let points_differ = match standing {
+            Some(s) => (tally.points - s.points as f64).abs() >= 1.0,
+            None => true,
+        };
+        if points_differ || standing.map(|s| s.position) != Some(*position) {
+            mismatches.push(StandingMismatch {
+                driver_id: id.to_string(),
+                points: Some(tally.points),
+                upstream_points: standing.map(|s| s.points),
+                position: Some(*position),
+                upstream_position: standing.map(|s| s.position),
+            });
+        }
+    }
+    // drivers in the upstream standings without any stored result
+    for (id, standing) in &upstream {
+        if !tallies.drivers.contains_key(id) {
+            mismatches.push(StandingMismatch {
+                driver_id: id.clone(),
+                points: None,
+                upstream_points: Some(standing.points),
+                position: None,
+                upstream_position: Some(standing.position),
+            });
+        }
+    }
+
+    Some(StandingsCheckResponse {
+        season: season.season,
+        round: race.round(),
+        consistent: mismatches.is_empty(),
+        drivers: local.len(),
+        mismatches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+
+    fn sprint(driver_id: &str, points: f64) -> SprintResult {
+        SprintResult {
+            driver_id: driver_id.to_string(),
+            constructor_id: "red_bull".to_string(),
+            position: 1,
+            position_text: "1".to_string(),
+            points: BigDecimal::try_from(points).unwrap(),
+        }
+    }
+
+    fn tallies(races: &[RaceResult], sprints: &[SprintResult]) -> HashMap<String, Tally> {
+        let mut map: HashMap<String, Tally> = HashMap::new();
+        for result in races {
+            map.entry(result.driver_id.clone())
+                .or_default()
+                .add_race(result);
+        }
+        for result in sprints {
+            map.entry(result.driver_id.clone())
+                .or_default()
+                .add_sprint(result);
+        }
+        map
+    }
+
+    fn order(tallies: &HashMap<String, Tally>) -> Vec<(i32, &str)> {
+        rank(tallies)
+            .into_iter()
+            .map(|(position, id, _)| (position, id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn sprint_points_count_but_not_their_wins() {
+        let map = tallies(
+            &[
+                RaceResult::sample("max", "red_bull", 2, 18.0),
+                RaceResult::sample("per", "red_bull", 1, 25.0),
+                RaceResult::sample("per", "red_bull", 10, 1.0),
+            ],
+            &[sprint("max", 8.0)],
+        );
+        assert_eq!(map["max"].points, 26.0);
+        assert_eq!(map["max"].wins(), 0);
+        assert_eq!(order(&map), vec![(1, "per"), (2, "max")]);
+    }
+
+    #[test]
+    fn countback_goes_down_to_the_first_difference() {
+        let map = tallies(
+            &[
+                RaceResult::sample("ham", "mercedes", 1, 25.0),
+                RaceResult::sample("ham", "mercedes", 3, 15.0),
+                RaceResult::sample("ham", "mercedes", 4, 12.0),
+                RaceResult::sample("bot", "mercedes", 1, 25.0),
+                RaceResult::sample("bot", "mercedes", 2, 18.0),
+                RaceResult::sample("bot", "mercedes", 6, 8.0),
+                RaceResult::sample("bot", "mercedes", 10, 1.0),
+            ],
+            &[],
+        );
+        // 52 points each with a win, the second place decides
+        assert_eq!(order(&map), vec![(1, "bot"), (2, "ham")]);
+    }
+
+    #[test]
+    fn unclassified_results_do_not_count_back() {
+        let mut retired = RaceResult::sample("alo", "alpine", 1, 0.0);
+        retired.position_text = "R".to_string();
+        let map = tallies(
+            &[retired, RaceResult::sample("oco", "alpine", 11, 0.0)],
+            &[],
+        );
+        assert_eq!(map["alo"].wins(), 0);
+        assert_eq!(order(&map), vec![(1, "oco"), (2, "alo")]);
+    }
+
+    #[test]
+    fn half_points_are_kept() {
+        let map = tallies(&[RaceResult::sample("ham", "mercedes", 1, 12.5)], &[]);
+        assert_eq!(map["ham"].points, 12.5);
+    }
+}
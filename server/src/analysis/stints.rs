@@ -0,0 +1,61 @@
+//! Stint analysis: splits each driver's race at the pit stops and measures the pace
+//! and tyre degradation of every stint.
+use crate::analysis::laps::{self, DriverLaps, LapSample};
+use crate::analysis::{fuel, stats};
+use crate::db::connection::PooledConnection;
+use crate::db::db_models::Race;
+use crate::models::{Stint, StintResponse};
+
+// a stint ends with the lap the driver pitted on.
+fn split_stints(laps: &[LapSample]) -> Vec<&[LapSample]> {
+    laps.split_inclusive(|l| l.pit_in).collect()
+}
+
+fn analyse_stint(number: i32, laps: &[LapSample]) -> Stint {
+    let clean = laps.iter().filter(|l| l.is_clean()).collect::<Vec<_>>();
+    let times = clean.iter().map(|l| l.time).collect::<Vec<f64>>();
+    let points = clean
+        .iter()
+        .map(|l| (l.lap as f64, l.time))
+        .collect::<Vec<(f64, f64)>>();
+    let start_lap = laps.first().map(|l| l.lap).unwrap_or_default();
+    let end_lap = laps.last().map(|l| l.lap).unwrap_or_default();
+
+    Stint {
+        stint: number,
+        start_lap,
+        end_lap,
+        laps: end_lap - start_lap + 1,
+        clean_laps: clean.len(),
+        mean_pace: stats::mean(&times),
+        median_pace: stats::median(&times),
+        degradation: stats::linear_slope(&points),
+    }
+}
+
+pub fn build(drivers: &[DriverLaps]) -> Vec<StintResponse> {
+    drivers
+        .iter()
+        .map(|driver| StintResponse {
+            driver_id: driver.driver_id.clone(),
+            code: driver.code.clone(),
+            color: driver.color.clone(),
+            stints: (1..)
+                .zip(split_stints(&driver.laps))
+                .map(|(number, laps)| analyse_stint(number, laps))
+                .collect(),
+        })
+        .collect()
+}
+
+pub async fn generate_response(
+    race: &Race,
+    fuel_per_lap: Option<f64>,
+    conn: &mut PooledConnection,
+) -> Vec<StintResponse> {
+    let mut drivers = laps::load(race, conn).await;
+    if let Some(per_lap) = fuel_per_lap {
+        fuel::apply(&mut drivers, per_lap);
+    }
+    build(&drivers)
+}
@@ -0,0 +1,69 @@
+//! Source of the current time for the endpoints relative to now, so that they can be
+//! checked against a known calendar. `CLOCK_NOW=2024-03-02T12:00:00Z` freezes the clock.
+use chrono::DateTime;
+use std::env;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Clock {
+    fixed: Option<SystemTime>,
+}
+
+impl Clock {
+    pub fn fixed(now: SystemTime) -> Self {
+        Self { fixed: Some(now) }
+    }
+
+    pub fn from_env() -> Self {
+        match env::var("CLOCK_NOW") {
+            Ok(v) => match DateTime::parse_from_rfc3339(&v) {
+                Ok(t) => Clock::fixed(t.into()),
+                Err(e) => {
+                    println!("Ignoring invalid CLOCK_NOW {}: {}", v, e);
+                    Clock::default()
+                }
+            },
+            Err(_) => Clock::default(),
+        }
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.fixed.unwrap_or_else(SystemTime::now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn fixed_clock_does_not_move() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_380_800);
+        let clock = Clock::fixed(now);
+
+        assert_eq!(clock.now(), now);
+        assert_eq!(clock.now(), now);
+    }
+
+    #[test]
+    fn default_clock_follows_the_system_time() {
+        let before = SystemTime::now();
+        let now = Clock::default().now();
+
+        assert!(now >= before && now <= SystemTime::now());
+    }
+
+    #[test]
+    fn clock_now_is_read_as_rfc3339() {
+        // the only test reading CLOCK_NOW, so that tests running in parallel do not race
+        env::set_var("CLOCK_NOW", "2024-03-02T12:00:00Z");
+        let fixed = Clock::from_env().now();
+        env::set_var("CLOCK_NOW", "2 March 2024");
+        let invalid = Clock::from_env();
+        env::remove_var("CLOCK_NOW");
+
+        assert_eq!(fixed, UNIX_EPOCH + Duration::from_secs(1_709_380_800));
+        assert!(invalid.fixed.is_none());
+    }
+}
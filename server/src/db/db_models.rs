@@ -1,19 +1,26 @@
 use crate::{
+    analysis::{points::PointsSystem, standings},
     db::connection::PooledConnection,
     models::{
-        ChartResponse, ConstructorResponse, DriverResponse, LapLineChartData, PitstopResponse,
-        RaceResponse, RaceResultResponse, SeasonResponse,
+        ChampionshipPosition, ChartResponse, ConstructorProfileResponse, ConstructorResponse,
+        ConstructorSeason, DriverCareerStats, DriverProfileResponse, DriverProgression,
+        DriverResponse, DriverTeam, FastestLapEntry, PageResponse, PitstopResponse, PodiumEntry,
+        PositionChartData, ProgressionResponse, RaceEventResponse, RaceResponse,
+        RaceResultResponse, RaceSessions, RaceWinner, SeasonCoverage, SeasonLeader, SeasonResponse,
     },
+    queries::{ConstructorListQuery, ConstructorSort, DriverListQuery, DriverSort, SortOrder},
 };
 use bigdecimal::{BigDecimal, ToPrimitive};
-use chrono::{NaiveDate, NaiveTime};
+use chrono::{Datelike, NaiveDate, NaiveTime};
+use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::result::Error;
+use diesel::sql_types::{Array, BigInt, Bool, Integer, Nullable, Numeric, Text, Timestamp};
 use ergast_rust::api::{Path, URLParams};
 use ergast_rust::ergast::Ergast;
 use serde::Serialize;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -25,6 +32,13 @@ fn combine_date_and_time(date_str: &str, time_str: &str) -> Result<SystemTime, c
     Ok(UNIX_EPOCH + std::time::Duration::from_secs(datetime.timestamp() as u64))
 }
 
+// start of a session in the schedule. sessions without a time are set to midnight.
+fn session_time(session: &Option<ergast_rust::models::Schedule>) -> Option<SystemTime> {
+    session
+        .as_ref()
+        .and_then(|s| combine_date_and_time(&s.date, s.time.as_deref().unwrap_or("00:00:00Z")).ok())
+}
+
 fn convert_system_time_to_string(time: SystemTime) -> String {
     let datetime = chrono::DateTime::<chrono::Utc>::from(time);
     datetime.format("%Y-%m-%d %H:%M:%S").to_string()
@@ -36,7 +50,9 @@ fn convert_system_time_to_string(time: SystemTime) -> String {
 pub struct Season {
     pub id: i32,
     pub season: i32,
+    #[allow(dead_code)]
     pub url: String,
+    #[allow(dead_code)]
     pub created_at: SystemTime,
 }
 
@@ -106,9 +122,9 @@ impl Season {
     }
 
     pub async fn generate_response(
+        now: SystemTime,
         conn: &mut PooledConnection,
     ) -> Result<Vec<SeasonResponse>, Error> {
-        use crate::db::schema::seasons::dsl::*;
         // check if season data is already in the database
         if !Season::is_exist(conn) {
             println!("Season data is not in the database. Fetch from Ergast API.");
@@ -116,44 +132,280 @@ impl Season {
             Season::post(conn).await;
         }
 
-        let results = seasons.load::<Season>(conn);
+        let results = Season::summaries(None, now, conn);
 
         match results {
-            Ok(s) => {
-                let mut v = s
-                    .iter()
-                    .map(|s| SeasonResponse {
-                        season: s.season,
-                        url: s.url.clone(),
-                    })
-                    .collect::<Vec<SeasonResponse>>();
-                v.reverse(); // descending order
-                Ok(v)
-            }
+            Ok(v) => Ok(v),
             Err(e) => {
                 println!("Error loading seasons: {}", e);
                 Err(e)
             }
         }
     }
+
+    // overview of one season. None if the season is not in the database.
+    pub async fn generate_summary(
+        year: i32,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Result<Option<SeasonResponse>, Error> {
+        if !Season::is_exist(conn) {
+            println!("Season data is not in the database. Fetch from Ergast API.");
+            Season::post(conn).await;
+        }
+        let season = match Season::find(year, conn) {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        if !Race::is_exist(&season, conn) {
+            println!("Race data is not in the database. Fetch from Ergast API.");
+            Race::post(&season, conn).await;
+        }
+        Ok(Season::summaries(Some(year), now, conn)?.pop())
+    }
+
+    pub fn find(season: i32, conn: &mut PooledConnection) -> Option<Season> {
+        use crate::db::schema::seasons;
+        seasons::table
+            .filter(seasons::season.eq(season))
+            .first::<Season>(conn)
+            .optional()
+            .unwrap_or_else(|e| {
+                println!("Error loading season {}: {}", season, e);
+                None
+            })
+    }
+
+    // season overviews from the stored data only, newest first. all seasons if `year` is None.
+    fn summaries(
+        year: Option<i32>,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Result<Vec<SeasonResponse>, Error> {
+        // rounds of the season, and how many of the completed ones have each kind of data
+        let rows = diesel::sql_query(
+            "SELECT s.season, s.url,
+                COUNT(r.id) AS rounds,
+                COUNT(r.id) FILTER (WHERE r.event_time <= $1) AS completed,
+                COUNT(r.id) FILTER (WHERE r.event_time <= $1
+                    AND EXISTS (SELECT 1 FROM race_results x WHERE x.race_id = r.id)) AS results,
+                COUNT(r.id) FILTER (WHERE r.event_time <= $1
+                    AND EXISTS (SELECT 1 FROM laptimes x WHERE x.race_id = r.id)) AS laps,
+                COUNT(r.id) FILTER (WHERE r.event_time <= $1
+                    AND EXISTS (SELECT 1 FROM pitstops x WHERE x.race_id = r.id)) AS pitstops,
+                COUNT(r.id) FILTER (WHERE r.event_time <= $1
+                    AND EXISTS (SELECT 1 FROM standings x WHERE x.race = r.id)) AS standings
+            FROM seasons s
+            LEFT JOIN races r ON r.season = s.id
+            WHERE ($2 IS NULL OR s.season = $2)
+            GROUP BY s.season, s.url
+            ORDER BY s.season DESC",
+        )
+        .bind::<Timestamp, _>(now)
+        .bind::<Nullable<Integer>, _>(year)
+        .load::<SeasonRow>(conn)?;
+
+        let drivers = Season::driver_leaders(year, now, conn)?;
+        let constructors = Season::constructor_leaders(year, conn)?;
+        let mut winners: HashMap<i32, Vec<RaceWinner>> = HashMap::new();
+        for w in Season::winners(year, conn)? {
+            winners.entry(w.season).or_default().push(RaceWinner {
+                round: w.round,
+                race_name: w.race_name,
+                driver_id: w.driver_id,
+                code: w.code.unwrap_or("NA".to_string()),
+                constructor_id: w.constructor_id,
+            });
+        }
+
+        let leader = |rows: &[LeaderRow], season: i32| {
+            rows.iter()
+                .find(|l| l.season == season)
+                .map(|l| SeasonLeader {
+                    id: l.id.clone(),
+                    points: l.points.to_f64().unwrap_or_default(),
+                    wins: l.wins,
+                })
+        };
+        let covered = |n: i64, completed: i64| completed > 0 && n == completed;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| SeasonResponse {
+                season: r.season,
+                driver_leader: leader(&drivers, r.season),
+                // a sum is only the table when every completed round is stored and every
+                // result counts, as it has since 1991
+                constructor_leader: if covered(r.results, r.completed)
+                    && PointsSystem::for_season(r.season).is_some()
+                {
+                    leader(&constructors, r.season)
+                } else {
+                    None
+                },
+                winners: winners.remove(&r.season).unwrap_or_default(),
+                url: r.url,
+                rounds: r.rounds,
+                completed_rounds: r.completed,
+                finished: r.rounds > 0 && r.completed == r.rounds,
+                coverage: SeasonCoverage {
+                    calendar: r.rounds > 0,
+                    results: covered(r.results, r.completed),
+                    laps: covered(r.laps, r.completed),
+                    pitstops: covered(r.pitstops, r.completed),
+                    standings: covered(r.standings, r.completed),
+                },
+            })
+            .collect())
+    }
+
+    // leader of the drivers' standings after the last completed round of each season, where
+    // those standings are stored. they apply the dropped scores of the older seasons.
+    fn driver_leaders(
+        year: Option<i32>,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Result<Vec<LeaderRow>, Error> {
+        diesel::sql_query(
+            "SELECT s.season, st.driver_id AS id, st.points::numeric AS points,
+                st.wins::bigint AS wins
+            FROM standings st
+            JOIN races r ON st.race = r.id
+            JOIN seasons s ON r.season = s.id
+            WHERE st.position = 1 AND ($1 IS NULL OR s.season = $1)
+                AND r.round = (SELECT MAX(x.round) FROM races x
+                    WHERE x.season = r.season AND x.event_time <= $2)",
+        )
+        .bind::<Nullable<Integer>, _>(year)
+        .bind::<Timestamp, _>(now)
+        .load::<LeaderRow>(conn)
+    }
+
+    // points leader of the constructors in each season, over the stored race and sprint
+    // results. ties go to the one with more wins.
+    fn constructor_leaders(
+        year: Option<i32>,
+        conn: &mut PooledConnection,
+    ) -> Result<Vec<LeaderRow>, Error> {
+        diesel::sql_query(
+            "SELECT DISTINCT ON (season) season, id, points, wins FROM (
+                SELECT s.season, p.constructor_id AS id, SUM(p.points) AS points,
+                    COUNT(*) FILTER (WHERE p.position_text = '1' AND NOT p.sprint) AS wins
+                FROM (
+                    SELECT race_id, driver_id, constructor_id, points, position_text,
+                        FALSE AS sprint
+                    FROM race_results
+                    UNION ALL
+                    SELECT race_id, driver_id, constructor_id, points, position_text,
+                        TRUE AS sprint
+                    FROM sprint_results
+                ) p
+                JOIN races r ON p.race_id = r.id
+                JOIN seasons s ON r.season = s.id
+                WHERE ($1 IS NULL OR s.season = $1)
+                GROUP BY s.season, p.constructor_id
+            ) t
+            ORDER BY season, points DESC, wins DESC, id",
+        )
+        .bind::<Nullable<Integer>, _>(year)
+        .load::<LeaderRow>(conn)
+    }
+
+    fn winners(year: Option<i32>, conn: &mut PooledConnection) -> Result<Vec<WinnerRow>, Error> {
+        diesel::sql_query(
+            "SELECT s.season, r.round, r.race_name, rr.driver_id, d.code, rr.constructor_id
+            FROM race_results rr
+            JOIN races r ON rr.race_id = r.id
+            JOIN seasons s ON r.season = s.id
+            JOIN drivers d ON rr.driver_id = d.id
+            WHERE rr.position_text = '1' AND ($1 IS NULL OR s.season = $1)
+            ORDER BY s.season, r.round",
+        )
+        .bind::<Nullable<Integer>, _>(year)
+        .load::<WinnerRow>(conn)
+    }
+}
+
+#[derive(QueryableByName)]
+struct SeasonRow {
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Text)]
+    url: String,
+    #[diesel(sql_type = BigInt)]
+    rounds: i64,
+    #[diesel(sql_type = BigInt)]
+    completed: i64,
+    #[diesel(sql_type = BigInt)]
+    results: i64,
+    #[diesel(sql_type = BigInt)]
+    laps: i64,
+    #[diesel(sql_type = BigInt)]
+    pitstops: i64,
+    #[diesel(sql_type = BigInt)]
+    standings: i64,
+}
+
+#[derive(QueryableByName)]
+struct LeaderRow {
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = Numeric)]
+    points: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    wins: i64,
 }
 
+#[derive(QueryableByName)]
+struct WinnerRow {
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Integer)]
+    round: i32,
+    #[diesel(sql_type = Text)]
+    race_name: String,
+    #[diesel(sql_type = Text)]
+    driver_id: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    code: Option<String>,
+    #[diesel(sql_type = Text)]
+    constructor_id: String,
+}
+
+// version of the columns filled by Race::post. bump it when a column is added to races,
+// so that the calendars stored before are fetched again:
+// 1: circuit_id and circuit_name
+// 2: sprint_time, so that stored sprint weekends are not taken for regular ones
+// 3: practice and qualifying times
+const CALENDAR_VERSION: i32 = 3;
+
 #[derive(Queryable, Selectable, Debug)]
 #[diesel(table_name = crate::db::schema::races)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 #[diesel(belongs_to(Season))]
-#[allow(dead_code)]
 pub struct Race {
     id: i32,
     season: i32,
     round: i32,
+    #[allow(dead_code)]
     url: String,
     race_name: String,
     event_time: SystemTime,
+    #[allow(dead_code)]
     created_at: SystemTime,
+    circuit_id: Option<String>,
+    circuit_name: Option<String>,
+    sprint_time: Option<SystemTime>,
+    first_practice_time: Option<SystemTime>,
+    second_practice_time: Option<SystemTime>,
+    third_practice_time: Option<SystemTime>,
+    qualifying_time: Option<SystemTime>,
+    calendar_version: i32,
 }
 
-#[derive(Insertable)]
+#[derive(Insertable, AsChangeset)]
 #[diesel(table_name = crate::db::schema::races)]
 pub struct NewRace<'a> {
     season: &'a i32,
@@ -161,6 +413,14 @@ pub struct NewRace<'a> {
     url: &'a str,
     race_name: &'a str,
     event_time: &'a SystemTime,
+    circuit_id: &'a str,
+    circuit_name: &'a str,
+    sprint_time: Option<SystemTime>,
+    first_practice_time: Option<SystemTime>,
+    second_practice_time: Option<SystemTime>,
+    third_practice_time: Option<SystemTime>,
+    qualifying_time: Option<SystemTime>,
+    calendar_version: i32,
 }
 
 impl Race {
@@ -176,17 +436,38 @@ impl Race {
             .expect("failed to fetch races");
 
         for race in response.table.races {
+            // Ergast has no start time for races before 2005
+            let time = race.time.as_deref().unwrap_or("00:00:00Z");
+            let event_time = match combine_date_and_time(&race.date, time) {
+                Ok(t) => t,
+                Err(e) => {
+                    println!("Skipping race {} {}: {}", race.season, race.round, e);
+                    continue;
+                }
+            };
             let season = Season::get(race.season, conn);
             let new_race = NewRace {
                 season: &season.id,
                 round: &race.round,
                 url: &race.url,
                 race_name: &race.race_name,
-                event_time: &combine_date_and_time(&race.date, &race.time.unwrap()).unwrap(),
+                event_time: &event_time,
+                circuit_id: &race.circuit.circuit_id,
+                circuit_name: &race.circuit.circuit_name,
+                sprint_time: session_time(&race.sprint),
+                first_practice_time: session_time(&race.first_practice),
+                second_practice_time: session_time(&race.second_practice),
+                third_practice_time: session_time(&race.third_practice),
+                qualifying_time: session_time(&race.qualifying),
+                calendar_version: CALENDAR_VERSION,
             };
             println!("Inserting race season:{} round:{}", race.season, race.round);
+            // races stored by an older version are updated in place, keeping their ids
             let result = diesel::insert_into(races::table)
                 .values(&new_race)
+                .on_conflict((races::season, races::round))
+                .do_update()
+                .set(&new_race)
                 .returning(Race::as_returning())
                 .get_result(conn);
 
@@ -196,6 +477,22 @@ impl Race {
         }
     }
 
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn round(&self) -> i32 {
+        self.round
+    }
+
+    pub fn circuit_id(&self) -> Option<&str> {
+        self.circuit_id.as_deref()
+    }
+
+    pub fn has_sprint(&self) -> bool {
+        self.sprint_time.is_some()
+    }
+
     pub fn get_races_in_season(season: &Season, conn: &mut PooledConnection) -> Vec<Race> {
         use crate::db::schema::races;
         races::table
@@ -206,6 +503,39 @@ impl Race {
             .collect::<Vec<Race>>()
     }
 
+    // races of the season which already took place, in round order.
+    // the calendar is fetched from Ergast API if it is not in the database.
+    pub async fn get_completed_in_season(
+        season: &Season,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Vec<Race> {
+        if !Race::is_exist(season, conn) {
+            println!("Race data is not in the database. Fetch from Ergast API.");
+            Race::post(season, conn).await;
+        }
+        let mut races = Race::get_races_in_season(season, conn);
+        races.retain(|r| !r.is_upcoming(now));
+        races.sort_by_key(|r| r.round);
+        races
+    }
+
+    // races of the season which are still to come, in round order.
+    pub async fn get_remaining_in_season(
+        season: &Season,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Vec<Race> {
+        if !Race::is_exist(season, conn) {
+            println!("Race data is not in the database. Fetch from Ergast API.");
+            Race::post(season, conn).await;
+        }
+        let mut races = Race::get_races_in_season(season, conn);
+        races.retain(|r| r.is_upcoming(now));
+        races.sort_by_key(|r| r.round);
+        races
+    }
+
     pub fn get(season: &Season, round: i32, conn: &mut PooledConnection) -> Option<Race> {
         use crate::db::schema::races;
         let result = races::table
@@ -214,12 +544,16 @@ impl Race {
         result.ok().and_then(|mut v| v.pop())
     }
 
+    // races stored before the columns of the current CALENDAR_VERSION were added count as
+    // missing, so that fetching the calendar again fills them in.
     pub fn is_exist(season: &Season, conn: &mut PooledConnection) -> bool {
-        let results = Race::get_races_in_season(&season, conn);
+        let results = Race::get_races_in_season(season, conn);
         if results.is_empty() {
             return false;
         }
-        true
+        results
+            .iter()
+            .all(|r| r.calendar_version >= CALENDAR_VERSION)
     }
 
     pub async fn generate_response(
@@ -227,10 +561,10 @@ impl Race {
         conn: &mut PooledConnection,
     ) -> Vec<RaceResponse> {
         // check if race data is already in the database
-        if !Race::is_exist(&season, conn) {
+        if !Race::is_exist(season, conn) {
             println!("Race data is not in the database. Fetch from Ergast API.");
             // if not, fetch race data from Ergast API and insert it into the database
-            Race::post(&season, conn).await;
+            Race::post(season, conn).await;
         }
 
         let results = Race::get_races_in_season(season, conn);
@@ -241,11 +575,164 @@ impl Race {
                 season: season.season,
                 round: race.round,
                 race_name: race.race_name.clone(),
-                circuit_name: "placeholder".to_string(),
+                circuit_name: race
+                    .circuit_name
+                    .clone()
+                    .unwrap_or("placeholder".to_string()),
                 date: convert_system_time_to_string(race.event_time),
             })
             .collect::<Vec<RaceResponse>>()
     }
+
+    // whether the race starts after `now`. a race starting at `now` has already started.
+    pub fn is_upcoming(&self, now: SystemTime) -> bool {
+        self.event_time > now
+    }
+
+    // seconds from `now` to the start of the race, negative once it has started.
+    fn seconds_until(&self, now: SystemTime) -> i64 {
+        match self.event_time.duration_since(now) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        }
+    }
+
+    // the first race starting after `now`, in any stored season.
+    pub fn next(now: SystemTime, conn: &mut PooledConnection) -> Option<Race> {
+        use crate::db::schema::races;
+        races::table
+            .filter(races::event_time.gt(now))
+            .order(races::event_time.asc())
+            .first::<Race>(conn)
+            .optional()
+            .unwrap_or_else(|e| {
+                println!("Error loading next race: {}", e);
+                None
+            })
+    }
+
+    // the latest race which started at or before `now`, in any stored season.
+    pub fn last(now: SystemTime, conn: &mut PooledConnection) -> Option<Race> {
+        use crate::db::schema::races;
+        races::table
+            .filter(races::event_time.le(now))
+            .order(races::event_time.desc())
+            .first::<Race>(conn)
+            .optional()
+            .unwrap_or_else(|e| {
+                println!("Error loading last race: {}", e);
+                None
+            })
+    }
+
+    // make sure the calendars around `now` are stored. the previous season is needed
+    // for the last race early in the year, before the first round, and the next one for
+    // the next race late in the year, after the last round.
+    async fn fetch_calendars(now: SystemTime, conn: &mut PooledConnection) {
+        if !Season::is_exist(conn) {
+            println!("Season data is not in the database. Fetch from Ergast API.");
+            Season::post(conn).await;
+        }
+        let year = chrono::DateTime::<chrono::Utc>::from(now).year();
+        for year in [year - 1, year] {
+            Race::fetch_calendar(year, conn).await;
+        }
+        if Race::next(now, conn).is_none() {
+            Race::fetch_calendar(year + 1, conn).await;
+        }
+    }
+
+    // a season missing from the stored seasons, e.g. one not published yet, is skipped.
+    async fn fetch_calendar(year: i32, conn: &mut PooledConnection) {
+        if let Some(season) = Season::find(year, conn) {
+            if !Race::is_exist(&season, conn) {
+                println!("Race data is not in the database. Fetch from Ergast API.");
+                Race::post(&season, conn).await;
+            }
+        }
+    }
+
+    pub async fn generate_next(
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Option<RaceEventResponse> {
+        Race::fetch_calendars(now, conn).await;
+        Race::next(now, conn).map(|race| race.event_response(now, conn))
+    }
+
+    // the last race with its podium and fastest lap. both are empty until the results
+    // are published.
+    pub async fn generate_last(
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Option<RaceEventResponse> {
+        Race::fetch_calendars(now, conn).await;
+        let race = Race::last(now, conn)?;
+        RaceResult::fetch_if_missing(&race, conn).await;
+        let mut results = RaceResult::get(&race, conn);
+        results.sort_by_key(|r| r.position);
+
+        let podium = results
+            .iter()
+            .filter(|r| r.position <= 3 && r.is_classified())
+            .map(|r| {
+                let driver = Driver::get_by_id(&r.driver_id, conn);
+                let constructor = Constructor::get_by_id(&r.constructor_id, conn);
+                PodiumEntry {
+                    position: r.position,
+                    driver_id: driver.id,
+                    code: driver.code.unwrap_or("NA".to_string()),
+                    given_name: driver.given_name,
+                    family_name: driver.family_name,
+                    constructor_id: constructor.id,
+                    constructor: constructor.name,
+                    points: r.points.to_f64().unwrap_or_default(),
+                    time_millis: r.time_millis,
+                }
+            })
+            .collect::<Vec<PodiumEntry>>();
+
+        let fastest_lap = results
+            .iter()
+            .find(|r| r.fastest_lap_rank == Some(1))
+            .map(|r| {
+                let driver = Driver::get_by_id(&r.driver_id, conn);
+                FastestLapEntry {
+                    driver_id: driver.id,
+                    code: driver.code.unwrap_or("NA".to_string()),
+                    constructor_id: r.constructor_id.clone(),
+                    lap_time: Laptime::best(&race, &r.driver_id, conn),
+                }
+            });
+
+        let mut response = race.event_response(now, conn);
+        response.podium = Some(podium);
+        response.fastest_lap = fastest_lap;
+        Some(response)
+    }
+
+    fn event_response(&self, now: SystemTime, conn: &mut PooledConnection) -> RaceEventResponse {
+        let season = Season::get_by_id(self.season, conn);
+        let seconds_until = self.seconds_until(now);
+        RaceEventResponse {
+            season: season.season,
+            round: self.round,
+            race_name: self.race_name.clone(),
+            circuit_id: self.circuit_id.clone(),
+            circuit_name: self.circuit_name.clone(),
+            date: convert_system_time_to_string(self.event_time),
+            seconds_until,
+            sessions: RaceSessions {
+                first_practice: self.first_practice_time.map(convert_system_time_to_string),
+                second_practice: self.second_practice_time.map(convert_system_time_to_string),
+                third_practice: self.third_practice_time.map(convert_system_time_to_string),
+                qualifying: self.qualifying_time.map(convert_system_time_to_string),
+                sprint: self.sprint_time.map(convert_system_time_to_string),
+            },
+            podium: None,
+            fastest_lap: None,
+        }
+    }
 }
 
 #[derive(Queryable, Selectable, Debug)]
@@ -259,6 +746,7 @@ pub struct Driver {
     pub family_name: String,
     pub date_of_birth: NaiveDate,
     pub nationality: String,
+    #[allow(dead_code)]
     pub created_at: SystemTime,
 }
 
@@ -280,6 +768,15 @@ impl Driver {
             .expect("loading error")
     }
 
+    pub fn find_by_id(id: &str, conn: &mut PooledConnection) -> Option<Driver> {
+        use crate::db::schema::drivers;
+        drivers::table
+            .filter(drivers::id.eq(id))
+            .first::<Driver>(conn)
+            .optional()
+            .expect("loading error")
+    }
+
     pub async fn post(conn: &mut PooledConnection) -> () {
         use crate::db::schema::drivers;
 
@@ -330,30 +827,219 @@ impl Driver {
         true
     }
 
-    pub fn generate_response(conn: &mut PooledConnection) -> Vec<DriverResponse> {
-        use crate::db::schema::drivers::dsl::*;
+    // build the filtered query. boxed so that it can be built twice for count and page.
+    fn filtered(query: &DriverListQuery) -> crate::db::schema::drivers::BoxedQuery<'static, Pg> {
+        use crate::db::schema::{drivers, race_results, races, seasons};
 
-        let results = drivers.load::<Driver>(conn);
+        let mut q = drivers::table.into_boxed();
+        if let Some(n) = &query.nationality {
+            q = q.filter(drivers::nationality.eq(n.clone()));
+        }
+        if let Some(year) = query.season {
+            let participants = race_results::table
+                .inner_join(races::table.inner_join(seasons::table))
+                .filter(seasons::season.eq(year))
+                .select(race_results::driver_id);
+            q = q.filter(drivers::id.eq_any(participants));
+        }
+        if let Some(pattern) = query.search_pattern() {
+            q = q.filter(
+                drivers::id
+                    .ilike(pattern.clone())
+                    .or(drivers::given_name.ilike(pattern.clone()))
+                    .or(drivers::family_name.ilike(pattern)),
+            );
+        }
+        q
+    }
 
-        match results {
+    pub fn generate_response(
+        query: &DriverListQuery,
+        conn: &mut PooledConnection,
+    ) -> PageResponse<DriverResponse> {
+        use crate::db::schema::drivers;
+
+        let total = Driver::filtered(query)
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap_or_else(|e| {
+                println!("Error counting drivers: {}", e);
+                0
+            });
+
+        let q = Driver::filtered(query);
+        let q = match (query.sort, query.order) {
+            (DriverSort::Id, SortOrder::Asc) => q.order(drivers::id.asc()),
+            (DriverSort::Id, SortOrder::Desc) => q.order(drivers::id.desc()),
+            (DriverSort::GivenName, SortOrder::Asc) => q.order(drivers::given_name.asc()),
+            (DriverSort::GivenName, SortOrder::Desc) => q.order(drivers::given_name.desc()),
+            (DriverSort::FamilyName, SortOrder::Asc) => q.order(drivers::family_name.asc()),
+            (DriverSort::FamilyName, SortOrder::Desc) => q.order(drivers::family_name.desc()),
+            (DriverSort::DateOfBirth, SortOrder::Asc) => q.order(drivers::date_of_birth.asc()),
+            (DriverSort::DateOfBirth, SortOrder::Desc) => q.order(drivers::date_of_birth.desc()),
+            (DriverSort::Nationality, SortOrder::Asc) => q.order(drivers::nationality.asc()),
+            (DriverSort::Nationality, SortOrder::Desc) => q.order(drivers::nationality.desc()),
+        };
+
+        let results = q
+            .then_order_by(drivers::id.asc()) // stable order between pages
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Driver>(conn);
+
+        let items = match results {
             Ok(s) => s
                 .into_iter()
-                .map(|d| DriverResponse {
-                    id: d.id,
-                    permanent_number: d.permanent_number,
-                    code: d.code,
-                    given_name: d.given_name,
-                    family_name: d.family_name,
-                    date_of_birth: d.date_of_birth,
-                    nationality: d.nationality,
-                })
+                .map(DriverResponse::from)
                 .collect::<Vec<DriverResponse>>(),
             Err(e) => {
                 println!("Error loading drivers: {}", e);
                 vec![]
             }
+        };
+
+        PageResponse {
+            total,
+            limit: query.limit(),
+            offset: query.offset(),
+            items,
         }
     }
+
+    // aggregate career statistics, championship positions and teams of a driver in SQL.
+    pub fn generate_profile(
+        id: &str,
+        conn: &mut PooledConnection,
+    ) -> Result<Option<DriverProfileResponse>, Error> {
+        let driver = match Driver::find_by_id(id, conn) {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+
+        let career = diesel::sql_query(
+            "SELECT
+                COUNT(*) FILTER (WHERE rr.position_text NOT IN ('W', 'F')) AS starts,
+                COUNT(*) FILTER (WHERE rr.position_text = '1') AS wins,
+                COUNT(*) FILTER (WHERE rr.position_text IN ('1', '2', '3')) AS podiums,
+                COALESCE(SUM(rr.points), 0)
+                    + (SELECT COALESCE(SUM(sr.points), 0) FROM sprint_results sr
+                        WHERE sr.driver_id = $1) AS points,
+                COUNT(*) FILTER (WHERE rr.position_text IN ('R', 'N')) AS dnfs,
+                MIN(rr.position) FILTER (WHERE rr.position_text ~ '^[0-9]+$') AS best_finish
+            FROM race_results rr
+            WHERE rr.driver_id = $1",
+        )
+        .bind::<Text, _>(id)
+        .get_result::<CareerRow>(conn)?;
+
+        let championships = diesel::sql_query(
+            "SELECT DISTINCT ON (s.season)
+                s.season, r.round,
+                r.round = (SELECT MAX(r2.round) FROM races r2 WHERE r2.season = r.season) AS is_final,
+                st.position, st.points, st.wins
+            FROM standings st
+            JOIN races r ON st.race = r.id
+            JOIN seasons s ON r.season = s.id
+            WHERE st.driver_id = $1
+            ORDER BY s.season, r.round DESC",
+        )
+        .bind::<Text, _>(id)
+        .load::<ChampionshipRow>(conn)?;
+
+        let teams = diesel::sql_query(
+            "SELECT c.id AS constructor_id, c.name,
+                MIN(s.season) AS first_season, MAX(s.season) AS last_season,
+                COUNT(*) AS starts
+            FROM race_results rr
+            JOIN constructors c ON rr.constructor_id = c.id
+            JOIN races r ON rr.race_id = r.id
+            JOIN seasons s ON r.season = s.id
+            WHERE rr.driver_id = $1
+            GROUP BY c.id, c.name
+            ORDER BY first_season, c.name",
+        )
+        .bind::<Text, _>(id)
+        .load::<TeamRow>(conn)?;
+
+        Ok(Some(DriverProfileResponse {
+            driver: DriverResponse::from(driver),
+            career: DriverCareerStats {
+                starts: career.starts,
+                wins: career.wins,
+                podiums: career.podiums,
+                points: career.points.to_f64().unwrap_or_default(),
+                dnfs: career.dnfs,
+                best_finish: career.best_finish,
+            },
+            championships: championships
+                .into_iter()
+                .map(|c| ChampionshipPosition {
+                    season: c.season,
+                    round: c.round,
+                    is_final: c.is_final,
+                    position: c.position,
+                    points: c.points,
+                    wins: c.wins,
+                })
+                .collect(),
+            teams: teams
+                .into_iter()
+                .map(|t| DriverTeam {
+                    constructor_id: t.constructor_id,
+                    name: t.name,
+                    first_season: t.first_season,
+                    last_season: t.last_season,
+                    starts: t.starts,
+                })
+                .collect(),
+        }))
+    }
+}
+
+#[derive(QueryableByName)]
+struct CareerRow {
+    #[diesel(sql_type = BigInt)]
+    starts: i64,
+    #[diesel(sql_type = BigInt)]
+    wins: i64,
+    #[diesel(sql_type = BigInt)]
+    podiums: i64,
+    #[diesel(sql_type = Numeric)]
+    points: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    dnfs: i64,
+    #[diesel(sql_type = Nullable<Integer>)]
+    best_finish: Option<i32>,
+}
+
+#[derive(QueryableByName)]
+struct ChampionshipRow {
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = Integer)]
+    round: i32,
+    #[diesel(sql_type = Bool)]
+    is_final: bool,
+    #[diesel(sql_type = Integer)]
+    position: i32,
+    #[diesel(sql_type = Integer)]
+    points: i32,
+    #[diesel(sql_type = Integer)]
+    wins: i32,
+}
+
+#[derive(QueryableByName)]
+struct TeamRow {
+    #[diesel(sql_type = Text)]
+    constructor_id: String,
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Integer)]
+    first_season: i32,
+    #[diesel(sql_type = Integer)]
+    last_season: i32,
+    #[diesel(sql_type = BigInt)]
+    starts: i64,
 }
 
 #[derive(Insertable)]
@@ -376,6 +1062,7 @@ pub struct Constructor {
     pub url: String,
     pub name: String,
     pub nationality: String,
+    #[allow(dead_code)]
     pub created_at: SystemTime,
 }
 
@@ -397,6 +1084,15 @@ impl Constructor {
             .expect("loading error")
     }
 
+    pub fn find_by_id(id: &str, conn: &mut PooledConnection) -> Option<Constructor> {
+        use crate::db::schema::constructors;
+        constructors::table
+            .filter(constructors::id.eq(id))
+            .first::<Constructor>(conn)
+            .optional()
+            .expect("loading error")
+    }
+
     pub async fn post(conn: &mut PooledConnection) -> () {
         use crate::db::schema::constructors;
 
@@ -439,28 +1135,164 @@ impl Constructor {
         true
     }
 
-    pub fn generate_response(conn: &mut PooledConnection) -> Vec<ConstructorResponse> {
-        use crate::db::schema::constructors::dsl::*;
+    // build the filtered query. boxed so that it can be built twice for count and page.
+    fn filtered(
+        query: &ConstructorListQuery,
+    ) -> crate::db::schema::constructors::BoxedQuery<'static, Pg> {
+        use crate::db::schema::{constructors, race_results, races, seasons};
 
-        let results = constructors.load::<Constructor>(conn);
+        let mut q = constructors::table.into_boxed();
+        if let Some(n) = &query.nationality {
+            q = q.filter(constructors::nationality.eq(n.clone()));
+        }
+        if let Some(year) = query.season {
+            let participants = race_results::table
+                .inner_join(races::table.inner_join(seasons::table))
+                .filter(seasons::season.eq(year))
+                .select(race_results::constructor_id);
+            q = q.filter(constructors::id.eq_any(participants));
+        }
+        if let Some(pattern) = query.search_pattern() {
+            q = q.filter(
+                constructors::id
+                    .ilike(pattern.clone())
+                    .or(constructors::name.ilike(pattern)),
+            );
+        }
+        q
+    }
 
-        match results {
+    pub fn generate_response(
+        query: &ConstructorListQuery,
+        conn: &mut PooledConnection,
+    ) -> PageResponse<ConstructorResponse> {
+        use crate::db::schema::constructors;
+
+        let total = Constructor::filtered(query)
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap_or_else(|e| {
+                println!("Error counting constructors: {}", e);
+                0
+            });
+
+        let q = Constructor::filtered(query);
+        let q = match (query.sort, query.order) {
+            (ConstructorSort::Id, SortOrder::Asc) => q.order(constructors::id.asc()),
+            (ConstructorSort::Id, SortOrder::Desc) => q.order(constructors::id.desc()),
+            (ConstructorSort::Name, SortOrder::Asc) => q.order(constructors::name.asc()),
+            (ConstructorSort::Name, SortOrder::Desc) => q.order(constructors::name.desc()),
+            (ConstructorSort::Nationality, SortOrder::Asc) => {
+                q.order(constructors::nationality.asc())
+            }
+            (ConstructorSort::Nationality, SortOrder::Desc) => {
+                q.order(constructors::nationality.desc())
+            }
+        };
+
+        let results = q
+            .then_order_by(constructors::id.asc()) // stable order between pages
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Constructor>(conn);
+
+        let items = match results {
             Ok(s) => s
                 .into_iter()
-                .map(|d| ConstructorResponse {
-                    id: d.id,
-                    url: d.url,
-                    name: d.name,
-                    nationality: d.nationality,
-                })
+                .map(ConstructorResponse::from)
                 .collect::<Vec<ConstructorResponse>>(),
             Err(e) => {
-                println!("Error loading drivers: {}", e);
+                println!("Error loading constructors: {}", e);
                 vec![]
             }
+        };
+
+        PageResponse {
+            total,
+            limit: query.limit(),
+            offset: query.offset(),
+            items,
         }
     }
 
+    // aggregate season-by-season results of a constructor in SQL. championship positions
+    // come from the computed standings, which fetch the missing results of the season first.
+    pub async fn generate_profile(
+        id: &str,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> Result<Option<ConstructorProfileResponse>, Error> {
+        use crate::db::schema::{race_results, races, seasons};
+
+        let constructor = match Constructor::find_by_id(id, conn) {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+
+        let entered = race_results::table
+            .inner_join(races::table.inner_join(seasons::table))
+            .filter(race_results::constructor_id.eq(id))
+            .select(seasons::season)
+            .distinct()
+            .load::<i32>(conn)?;
+        let mut positions = HashMap::new();
+        for year in entered {
+            let season = match Season::find(year, conn) {
+                Some(s) => s,
+                None => continue,
+            };
+            let standings = standings::constructors_after(&season, i32::MAX, now, conn).await;
+            if let Some(s) = standings.unwrap_or_default().iter().find(|s| s.id == id) {
+                positions.insert(year, s.position);
+            }
+        }
+
+        let seasons = diesel::sql_query(
+            "SELECT s.season,
+                COUNT(DISTINCT rr.race_id) AS races,
+                ARRAY_AGG(DISTINCT rr.driver_id) AS drivers,
+                COALESCE(SUM(rr.points), 0)
+                    + (SELECT COALESCE(SUM(sr.points), 0) FROM sprint_results sr
+                        JOIN races r2 ON sr.race_id = r2.id
+                        WHERE sr.constructor_id = $1 AND r2.season = s.id) AS points,
+                COUNT(*) FILTER (WHERE rr.position_text = '1') AS wins,
+                COUNT(*) FILTER (WHERE rr.position_text IN ('1', '2', '3')) AS podiums,
+                MIN(rr.position) FILTER (WHERE rr.position_text ~ '^[0-9]+$') AS best_finish,
+                COUNT(*) FILTER (WHERE rr.position_text NOT IN ('W', 'F')) AS starts,
+                COUNT(*) FILTER (WHERE rr.position_text IN ('R', 'N')) AS dnfs
+            FROM race_results rr
+            JOIN races r ON rr.race_id = r.id
+            JOIN seasons s ON r.season = s.id
+            WHERE rr.constructor_id = $1
+            GROUP BY s.id, s.season
+            ORDER BY s.season",
+        )
+        .bind::<Text, _>(id)
+        .load::<ConstructorSeasonRow>(conn)?;
+
+        let color = constructor.team_color().to_string();
+        Ok(Some(ConstructorProfileResponse {
+            constructor: ConstructorResponse::from(constructor),
+            color,
+            seasons: seasons
+                .into_iter()
+                .map(|s| ConstructorSeason {
+                    season: s.season,
+                    races: s.races,
+                    drivers: s.drivers,
+                    points: s.points.to_f64().unwrap_or_default(),
+                    wins: s.wins,
+                    podiums: s.podiums,
+                    best_finish: s.best_finish,
+                    starts: s.starts,
+                    dnfs: s.dnfs,
+                    dnf_rate: (s.starts > 0).then(|| s.dnfs as f64 / s.starts as f64),
+                    championship_position: positions.get(&s.season).copied(),
+                })
+                .collect(),
+        }))
+    }
+
     // return color hex code for the team
     pub fn team_color(&self) -> &str {
         match self.id.as_str() {
@@ -479,6 +1311,28 @@ impl Constructor {
     }
 }
 
+#[derive(QueryableByName)]
+struct ConstructorSeasonRow {
+    #[diesel(sql_type = Integer)]
+    season: i32,
+    #[diesel(sql_type = BigInt)]
+    races: i64,
+    #[diesel(sql_type = Array<Text>)]
+    drivers: Vec<String>,
+    #[diesel(sql_type = Numeric)]
+    points: BigDecimal,
+    #[diesel(sql_type = BigInt)]
+    wins: i64,
+    #[diesel(sql_type = BigInt)]
+    podiums: i64,
+    #[diesel(sql_type = Nullable<Integer>)]
+    best_finish: Option<i32>,
+    #[diesel(sql_type = BigInt)]
+    starts: i64,
+    #[diesel(sql_type = BigInt)]
+    dnfs: i64,
+}
+
 #[derive(Insertable)]
 #[diesel(table_name = crate::db::schema::constructors)]
 pub struct NewConstructor<'a> {
@@ -492,14 +1346,19 @@ pub struct NewConstructor<'a> {
 #[diesel(table_name = crate::db::schema::standings)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Standing {
+    #[allow(dead_code)]
     pub id: i32,
+    #[allow(dead_code)]
     pub race: i32,
     pub driver_id: String,
     pub constructor_id: String,
     pub position: i32,
+    #[allow(dead_code)]
     pub position_text: String,
     pub points: i32,
+    #[allow(dead_code)]
     pub wins: i32,
+    #[allow(dead_code)]
     pub created_at: SystemTime,
 }
 
@@ -531,12 +1390,13 @@ impl Standing {
             .await
             .expect("failed to fetch standings");
 
-        let standing_list = &response
-            .table
-            .standings_lists
-            .get(0)
-            .unwrap()
-            .driver_standings;
+        let standing_list = match response.table.standings_lists.first() {
+            Some(l) => &l.driver_standings,
+            None => {
+                println!("No standing data");
+                return;
+            }
+        };
         for standing in standing_list {
             let driver = Driver::get(conn)
                 .into_iter()
@@ -544,7 +1404,7 @@ impl Standing {
                 .unwrap();
             let constructor = Constructor::get(conn)
                 .into_iter()
-                .find(|c| c.id == standing.constructors.get(0).unwrap().constructor_id)
+                .find(|c| c.id == standing.constructors.first().unwrap().constructor_id)
                 .unwrap();
             let new_standing = NewStanding {
                 race: &race.id,
@@ -583,10 +1443,10 @@ impl Standing {
         race: &Race,
         conn: &mut PooledConnection,
     ) -> ChartResponse<String, i32> {
-        if !Standing::is_exist(&race, conn) {
+        if !Standing::is_exist(race, conn) {
             println!("Standing data is not in the database. Fetch from Ergast API.");
             // if not, fetch standing data from Ergast API and insert it into the database
-            Standing::post(&race, conn).await;
+            Standing::post(race, conn).await;
         }
 
         let results = Standing::get(race, conn);
@@ -607,6 +1467,68 @@ impl Standing {
             color: Some(color),
         }
     }
+
+    // cumulative points and position of every driver after each completed round of the season.
+    pub async fn generate_progression(
+        season: &Season,
+        now: SystemTime,
+        conn: &mut PooledConnection,
+    ) -> ProgressionResponse {
+        use crate::db::schema::{constructors, drivers, races, standings};
+
+        // fetch standings of completed rounds which are not in the database yet
+        let races = Race::get_completed_in_season(season, now, conn).await;
+        for race in &races {
+            if !Standing::is_exist(race, conn) {
+                println!("Standing data is not in the database. Fetch from Ergast API.");
+                Standing::post(race, conn).await;
+            }
+        }
+
+        let rows = standings::table
+            .inner_join(races::table)
+            .inner_join(drivers::table)
+            .inner_join(constructors::table)
+            .filter(races::season.eq(season.id))
+            .order(races::round.asc())
+            .load::<(Standing, Race, Driver, Constructor)>(conn)
+            .expect("loading error");
+
+        let mut map: HashMap<String, DriverProgression> = HashMap::new();
+        for (standing, race, driver, constructor) in rows {
+            let entry = map
+                .entry(driver.id.clone())
+                .or_insert_with(|| DriverProgression {
+                    driver_id: driver.id,
+                    code: driver.code.unwrap_or("NA".to_string()),
+                    color: String::new(),
+                    rounds: Vec::new(),
+                    points: Vec::new(),
+                    positions: Vec::new(),
+                });
+            // rows are in round order, so the color ends up being the latest team's
+            entry.color = constructor.team_color().to_string();
+            entry.rounds.push(race.round);
+            entry.points.push(standing.points);
+            entry.positions.push(standing.position);
+        }
+
+        // order by the latest standing: latest round first, then position
+        let mut drivers = map.into_values().collect::<Vec<DriverProgression>>();
+        drivers.sort_by_key(|d| {
+            (
+                std::cmp::Reverse(d.rounds.last().copied()),
+                d.positions.last().copied(),
+            )
+        });
+
+        ProgressionResponse {
+            season: season.season,
+            rounds: races.iter().map(|r| r.round).collect(),
+            race_names: races.iter().map(|r| r.race_name.clone()).collect(),
+            drivers,
+        }
+    }
 }
 
 #[derive(Insertable)]
@@ -625,12 +1547,14 @@ pub struct NewStanding<'a> {
 #[diesel(table_name = crate::db::schema::laptimes)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Laptime {
+    #[allow(dead_code)]
     pub id: i32,
     pub race_id: i32,
     pub driver_id: String,
     pub lap_number: i32,
     pub lap_time: String,
     pub position: i32,
+    #[allow(dead_code)]
     pub created_at: SystemTime,
 }
 
@@ -677,7 +1601,7 @@ impl Laptime {
             .await
             .expect("failed to fetch laps");
 
-        let laps = &response.table.races.get(0).unwrap().laps;
+        let laps = &response.table.races.first().unwrap().laps;
 
         let laps = match laps {
             Some(l) => l,
@@ -726,6 +1650,34 @@ impl Laptime {
         true
     }
 
+    // fetch result, laptime and pitstop data of the race from Ergast API if it is not in the database.
+    // results come first because Laptime::get joins them.
+    pub async fn fetch_if_missing(race: &Race, conn: &mut PooledConnection) {
+        RaceResult::fetch_if_missing(race, conn).await;
+        if !Laptime::is_exist(race, conn) {
+            println!("Laptime data is not in the database. Fetch from Ergast API.");
+            Laptime::post(race, conn).await;
+        }
+        if !Pitstop::is_exist(race, conn) {
+            println!("Pitstop data is not in the database. Fetch from Ergast API.");
+            Pitstop::post(race, conn).await;
+        }
+    }
+
+    // best lap of the driver in seconds, None if the laps of the race are not stored.
+    pub fn best(race: &Race, driver_id: &str, conn: &mut PooledConnection) -> Option<f64> {
+        use crate::db::schema::laptimes;
+        laptimes::table
+            .filter(laptimes::race_id.eq(race.id))
+            .filter(laptimes::driver_id.eq(driver_id))
+            .select(laptimes::lap_time)
+            .load::<String>(conn)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|t| Laptime::convert_lap_time_text_to_f64(t).ok())
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
     // convert text formatted like "m:ss.SSS" to f64
     pub fn convert_lap_time_text_to_f64(lap_time: &str) -> Result<f64, &str> {
         let parts: Vec<&str> = lap_time.split(':').collect();
@@ -756,63 +1708,47 @@ impl Laptime {
         Ok(minutes * 60.0 + seconds + milliseconds)
     }
 
-    pub async fn generate_response(
+    // position of every driver on each lap, with the grid position as lap 0.
+    pub async fn generate_position_response(
         race: &Race,
-        exclude_pitstop: bool,
         conn: &mut PooledConnection,
-    ) -> Vec<LapLineChartData> {
-        if !Laptime::is_exist(&race, conn) {
-            println!("Laptime data is not in the database. Fetch from Ergast API.");
-            // if not, fetch laptime data from Ergast API and insert it into the database
-            Laptime::post(&race, conn).await;
-        }
-        if !Pitstop::is_exist(&race, conn) {
-            println!("Pitstop data is not in the database. Fetch from Ergast API.");
-            // if not, fetch pitstop data from Ergast API and insert it into the database
-            Pitstop::post(&race, conn).await;
-        }
-
-        let laps = Laptime::get(&race, conn);
-        let mut map = HashMap::new();
-        let mut pitstop_set = HashSet::new(); // set to chech if the driver pitted in the lap
-
-        
-        for (_laptime, pitstop, driver, _race_result) in &laps {
-            if let Some(pit) = pitstop {
-                pitstop_set.insert((driver.id.clone(), pit.lap_number - 1)); // add inlap.
-                pitstop_set.insert((driver.id.clone(), pit.lap_number));
-                pitstop_set.insert((driver.id.clone(), pit.lap_number + 1)); // add outlap.
-            }
-        }
-
-        for (laptime, _pitstop, driver, race_result) in laps {
-            let time = Laptime::convert_lap_time_text_to_f64(&laptime.lap_time);
-
-            // skip if exclude_pitstop is true and the driver pitted in the lap
-            if exclude_pitstop && pitstop_set.contains(&(driver.id.clone(), laptime.lap_number)) {
-                continue;
-            }
-
-            // get reference to the entry in the map
-            let entry = map
-                .entry(driver.id.clone())
-                .or_insert(LapLineChartData::new(driver.id, race_result, conn));
-
-            // push lap number into x axis
+    ) -> Vec<PositionChartData> {
+        Laptime::fetch_if_missing(race, conn).await;
+
+        let mut laps = Laptime::get(race, conn);
+        laps.sort_by_key(|(laptime, _, _, _)| laptime.lap_number);
+
+        // grid 0 means a pit lane start, which is shown behind the whole field
+        let field_size = RaceResult::get(race, conn).len() as i32;
+
+        let mut map: HashMap<String, PositionChartData> = HashMap::new();
+        for (laptime, pitstop, driver, race_result) in laps {
+            let entry = map.entry(driver.id.clone()).or_insert_with(|| {
+                let constructor = Constructor::get_by_id(&race_result.constructor_id, conn);
+                let grid = if race_result.grid > 0 {
+                    race_result.grid
+                } else {
+                    field_size
+                };
+                PositionChartData {
+                    driver_id: driver.id,
+                    code: driver.code.unwrap_or("NA".to_string()),
+                    position: race_result.position,
+                    laps: vec![0],
+                    positions: vec![grid],
+                    pit_laps: Vec::new(),
+                    color: constructor.team_color().to_string(),
+                }
+            });
             entry.laps.push(laptime.lap_number);
-
-            // push lap time into y axis
-            entry.laptime.push(time.unwrap());
+            entry.positions.push(laptime.position);
+            if pitstop.is_some() {
+                entry.pit_laps.push(laptime.lap_number);
+            }
         }
 
-        // convert map to vector
-        let mut vec = map
-            .into_iter()
-            .map(|(_, v)| v)
-            .collect::<Vec<LapLineChartData>>();
-
-        // vec.sort_by(|a, b| a.driver_id.partial_cmp(&b.driver_id).unwrap());
-        vec.sort_by(|a, b| a.position.partial_cmp(&b.position).unwrap());
+        let mut vec = map.into_values().collect::<Vec<PositionChartData>>();
+        vec.sort_by_key(|d| d.position);
         vec
     }
 }
@@ -869,7 +1805,7 @@ impl Pitstop {
             .await
             .expect("failed to fetch pitstops");
 
-        let pitstops = &response.table.races.get(0).unwrap().pitstops;
+        let pitstops = &response.table.races.first().unwrap().pitstops;
 
         let pitstops = match pitstops {
             Some(l) => l,
@@ -927,12 +1863,12 @@ impl Pitstop {
         race: &Race,
         conn: &mut PooledConnection,
     ) -> Vec<PitstopResponse> {
-        if !Pitstop::is_exist(&race, conn) {
+        if !Pitstop::is_exist(race, conn) {
             println!("Pitstop data is not in the database. Fetch from Ergast API.");
             // if not, fetch pitstop data from Ergast API and insert it into the database
-            Pitstop::post(&race, conn).await;
+            Pitstop::post(race, conn).await;
         }
-        let pitstops = Pitstop::get(&race, conn);
+        let pitstops = Pitstop::get(race, conn);
         let vec = pitstops
             .iter()
             .map(|pitstop| PitstopResponse {
@@ -957,6 +1893,12 @@ pub struct NewPitstop<'a> {
     pub duration: &'a BigDecimal,
 }
 
+// version of the columns filled by RaceResult::post. bump it when a column is added to
+// race_results, so that the results stored before are fetched again:
+// 1: fastest_lap_rank
+// 2: time_millis, which the winning margins need
+const RESULTS_VERSION: i32 = 2;
+
 #[derive(Queryable, Selectable, Debug, Serialize)]
 #[diesel(table_name = crate::db::schema::race_results)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -972,6 +1914,9 @@ pub struct RaceResult {
     pub status: String,
     pub points: BigDecimal,
     pub created_at: SystemTime,
+    pub fastest_lap_rank: Option<i32>,
+    pub time_millis: Option<i64>, // race time of the drivers on the lead lap
+    pub results_version: i32,
 }
 
 impl RaceResult {
@@ -1002,9 +1947,9 @@ impl RaceResult {
             .await
             .expect("failed to fetch results");
 
-        let results = &response.table.races.get(0).unwrap().results;
-
-        let results = match results {
+        // the race is listed without results until they are published
+        let races = &response.table.races;
+        let results = match races.first().and_then(|r| r.results.as_ref()) {
             Some(l) => l,
             None => {
                 println!("No race result data");
@@ -1025,11 +1970,22 @@ impl RaceResult {
                 laps: &result.laps,
                 status: &result.status,
                 points: &result.points.to_string().parse::<BigDecimal>().unwrap(),
+                fastest_lap_rank: result.fastest_lap.as_ref().and_then(|f| f.rank),
+                time_millis: result
+                    .time
+                    .as_ref()
+                    .and_then(|t| t.millis.as_ref())
+                    .and_then(|m| m.parse::<i64>().ok()),
+                results_version: RESULTS_VERSION,
             };
 
             println!("Inserting race_result {} {}", &race.id, &driver.id,);
+            // results stored by an older version are updated in place
             let result = diesel::insert_into(race_results::table)
                 .values(&new_race_result)
+                .on_conflict((race_results::race_id, race_results::driver_id))
+                .do_update()
+                .set(&new_race_result)
                 .returning(RaceResult::as_returning())
                 .get_result(conn);
 
@@ -1040,26 +1996,50 @@ impl RaceResult {
                 );
             }
         }
+        // updated results are rated again, along with every race after them
+        if let Err(e) = DriverRating::delete_for_races(&[race.id], conn) {
+            println!("Error resetting ratings of race {}: error: {}", &race.id, e);
+        }
     }
 
+    // results stored before the columns of the current RESULTS_VERSION were added count as
+    // missing, so that fetching them again fills them in.
     pub fn is_exist(race: &Race, conn: &mut PooledConnection) -> bool {
         let results = RaceResult::get(race, conn);
         if results.is_empty() {
             return false;
         }
-        true
+        results.iter().all(|r| r.results_version >= RESULTS_VERSION)
+    }
+
+    pub async fn fetch_if_missing(race: &Race, conn: &mut PooledConnection) {
+        if !RaceResult::is_exist(race, conn) {
+            println!("RaceResult data is not in the database. Fetch from Ergast API.");
+            RaceResult::post(race, conn).await;
+        }
+    }
+
+    // a numeric position_text means the driver was classified. the others are R (retired),
+    // N (not classified), D (disqualified), E (excluded), W (withdrawn) and F (failed to qualify).
+    pub fn is_classified(&self) -> bool {
+        self.position_text.parse::<i32>().is_ok()
+    }
+
+    // withdrawn entries and non-qualifiers did not start.
+    pub fn is_starter(&self) -> bool {
+        self.position_text != "W" && self.position_text != "F"
     }
 
     pub async fn generate_response(
         race: &Race,
         conn: &mut PooledConnection,
     ) -> Vec<RaceResultResponse> {
-        if !RaceResult::is_exist(&race, conn) {
+        if !RaceResult::is_exist(race, conn) {
             println!("RaceResult data is not in the database. Fetch from Ergast API.");
             // if not, fetch RaceResult data from Ergast API and insert it into the database
-            RaceResult::post(&race, conn).await;
+            RaceResult::post(race, conn).await;
         }
-        let race_results = RaceResult::get(&race, conn);
+        let race_results = RaceResult::get(race, conn);
         let mut vec = Vec::new();
         for race_result in race_results {
             let driver = Driver::get_by_id(&race_result.driver_id, conn);
@@ -1081,7 +2061,30 @@ impl RaceResult {
     }
 }
 
-#[derive(Insertable)]
+#[cfg(test)]
+impl RaceResult {
+    // a finished result for unit tests. position_text is the position unless it is changed.
+    pub fn sample(driver_id: &str, constructor_id: &str, position: i32, points: f64) -> RaceResult {
+        RaceResult {
+            id: 0,
+            race_id: 0,
+            driver_id: driver_id.to_string(),
+            constructor_id: constructor_id.to_string(),
+            position,
+            position_text: position.to_string(),
+            grid: position,
+            laps: 50,
+            status: "Finished".to_string(),
+            points: BigDecimal::try_from(points).unwrap(),
+            created_at: UNIX_EPOCH,
+            fastest_lap_rank: None,
+            time_millis: None,
+            results_version: RESULTS_VERSION,
+        }
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
 #[diesel(table_name = crate::db::schema::race_results)]
 pub struct NewRaceResult<'a> {
     pub race_id: &'a i32,
@@ -1093,4 +2096,243 @@ pub struct NewRaceResult<'a> {
     pub laps: &'a i32,
     pub status: &'a String,
     pub points: &'a BigDecimal,
+    pub fastest_lap_rank: Option<i32>,
+    pub time_millis: Option<i64>,
+    pub results_version: i32,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::db::schema::sprint_results)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SprintResult {
+    pub driver_id: String,
+    pub constructor_id: String,
+    pub position: i32,
+    pub position_text: String,
+    pub points: BigDecimal,
+}
+
+impl SprintResult {
+    pub fn get(race: &Race, conn: &mut PooledConnection) -> Vec<SprintResult> {
+        use crate::db::schema::sprint_results;
+        sprint_results::table
+            .filter(sprint_results::race_id.eq(race.id))
+            .select(SprintResult::as_select())
+            .load::<SprintResult>(conn)
+            .expect("loading error")
+    }
+
+    pub async fn post(race: &Race, conn: &mut PooledConnection) {
+        use crate::db::schema::sprint_results;
+
+        let season = Season::get_by_id(race.season, conn);
+        let path = Path {
+            year: season.season,
+            round: Some(race.round),
+        };
+
+        let params = URLParams {
+            limit: 100,
+            offset: 0,
+        };
+        let response = Ergast::sprint(path, params)
+            .await
+            .expect("failed to fetch sprint results");
+
+        let results = match response.table.races.first() {
+            Some(r) => &r.sprint_results,
+            None => {
+                println!("No sprint result data");
+                return;
+            }
+        };
+
+        for result in results {
+            let driver = Driver::get_by_id(&result.driver.driver_id, conn);
+            let constructor = Constructor::get_by_id(&result.constructor.constructor_id, conn);
+            let new_sprint_result = NewSprintResult {
+                race_id: &race.id,
+                driver_id: &driver.id,
+                constructor_id: &constructor.id,
+                position: &result.position,
+                position_text: &result.position_text,
+                points: &result.points.to_string().parse::<BigDecimal>().unwrap(),
+            };
+
+            println!("Inserting sprint_result {} {}", &race.id, &driver.id);
+            let result = diesel::insert_into(sprint_results::table)
+                .values(&new_sprint_result)
+                .returning(SprintResult::as_returning())
+                .get_result(conn);
+
+            if let Err(e) = result {
+                println!(
+                    "Error inserting sprint_result {} {}: error: {}",
+                    &race.id, &driver.id, e
+                );
+            }
+        }
+    }
+
+    pub fn is_exist(race: &Race, conn: &mut PooledConnection) -> bool {
+        !SprintResult::get(race, conn).is_empty()
+    }
+
+    // fetch the results of the race's sprint, if it had one.
+    pub async fn fetch_if_missing(race: &Race, conn: &mut PooledConnection) {
+        if race.has_sprint() && !SprintResult::is_exist(race, conn) {
+            println!("SprintResult data is not in the database. Fetch from Ergast API.");
+            SprintResult::post(race, conn).await;
+        }
+    }
+
+    // classified like a grand prix result, see `RaceResult::is_classified`.
+    pub fn is_classified(&self) -> bool {
+        self.position_text.parse::<i32>().is_ok()
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::db::schema::sprint_results)]
+pub struct NewSprintResult<'a> {
+    pub race_id: &'a i32,
+    pub driver_id: &'a String,
+    pub constructor_id: &'a String,
+    pub position: &'a i32,
+    pub position_text: &'a String,
+    pub points: &'a BigDecimal,
+}
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::db::schema::lap_flags)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LapFlag {
+    pub lap_number: i32,
+    pub neutralized: bool,
+}
+
+impl LapFlag {
+    pub fn get(race: &Race, conn: &mut PooledConnection) -> Vec<LapFlag> {
+        use crate::db::schema::lap_flags;
+        lap_flags::table
+            .filter(lap_flags::race_id.eq(race.id))
+            .order(lap_flags::lap_number.asc())
+            .select(LapFlag::as_select())
+            .load::<LapFlag>(conn)
+            .expect("loading error")
+    }
+
+    pub fn post(race: &Race, flags: &[NewLapFlag], conn: &mut PooledConnection) {
+        use crate::db::schema::lap_flags;
+
+        println!("Inserting lap flags {}", &race.id);
+        let result = diesel::insert_into(lap_flags::table)
+            .values(flags)
+            .execute(conn);
+
+        if let Err(e) = result {
+            println!("Error inserting lap flags {}: {}", &race.id, e);
+        }
+    }
+
+    pub fn is_exist(race: &Race, conn: &mut PooledConnection) -> bool {
+        let results = LapFlag::get(race, conn);
+        if results.is_empty() {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::db::schema::lap_flags)]
+pub struct NewLapFlag {
+    pub race_id: i32,
+    pub lap_number: i32,
+    pub field_median: f64,
+    pub neutralized: bool,
+}
+
+// the snapshots are only written here. analysis::ratings reads them with SQL.
+pub struct DriverRating;
+
+impl DriverRating {
+    pub fn post(ratings: &[NewDriverRating], conn: &mut PooledConnection) -> Result<usize, Error> {
+        use crate::db::schema::driver_ratings;
+        diesel::insert_into(driver_ratings::table)
+            .values(ratings)
+            .execute(conn)
+    }
+
+    // record the race as rated, also when it had no starters and so no snapshots.
+    pub fn mark_rated(race_id: i32, conn: &mut PooledConnection) -> Result<usize, Error> {
+        use crate::db::schema::rated_races;
+        diesel::insert_into(rated_races::table)
+            .values(rated_races::race_id.eq(race_id))
+            .execute(conn)
+    }
+
+    // delete the snapshots of the races and mark them as unrated.
+    pub fn delete_for_races(race_ids: &[i32], conn: &mut PooledConnection) -> Result<usize, Error> {
+        use crate::db::schema::{driver_ratings, rated_races};
+        diesel::delete(rated_races::table.filter(rated_races::race_id.eq_any(race_ids)))
+            .execute(conn)?;
+        diesel::delete(driver_ratings::table.filter(driver_ratings::race_id.eq_any(race_ids)))
+            .execute(conn)
+    }
+
+    // ids of the races which were rated since their results were last stored.
+    pub fn rated_races(conn: &mut PooledConnection) -> Result<Vec<i32>, Error> {
+        use crate::db::schema::rated_races;
+        rated_races::table
+            .select(rated_races::race_id)
+            .load::<i32>(conn)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::db::schema::driver_ratings)]
+pub struct NewDriverRating {
+    pub race_id: i32,
+    pub driver_id: String,
+    pub rating: f64,
+    pub races: i32, // races rated so far, including this one
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn race(round: i32, event_time: SystemTime) -> Race {
+        Race {
+            id: round,
+            season: 1,
+            round,
+            url: String::new(),
+            race_name: format!("Round {}", round),
+            event_time,
+            created_at: UNIX_EPOCH,
+            circuit_id: None,
+            circuit_name: None,
+            sprint_time: None,
+            first_practice_time: None,
+            second_practice_time: None,
+            third_practice_time: None,
+            qualifying_time: None,
+            calendar_version: CALENDAR_VERSION,
+        }
+    }
+
+    #[test]
+    fn seconds_until_turns_negative_at_the_start() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_709_391_600); // 2024-03-02T15:00:00Z
+        let race = race(1, start);
+
+        assert_eq!(race.seconds_until(start - HOUR), 3600);
+        assert_eq!(race.seconds_until(start), 0);
+        assert_eq!(race.seconds_until(start + HOUR), -3600);
+    }
 }
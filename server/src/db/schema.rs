@@ -10,6 +10,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    driver_ratings (id) {
+        id -> Int4,
+        race_id -> Int4,
+        driver_id -> Text,
+        rating -> Float8,
+        races -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     drivers (id) {
         id -> Text,
@@ -23,6 +34,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    lap_flags (id) {
+        id -> Int4,
+        race_id -> Int4,
+        lap_number -> Int4,
+        field_median -> Float8,
+        neutralized -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     laptimes (id) {
         id -> Int4,
@@ -61,6 +83,9 @@ diesel::table! {
         status -> Text,
         points -> Numeric,
         created_at -> Timestamp,
+        fastest_lap_rank -> Nullable<Int4>,
+        time_millis -> Nullable<Int8>,
+        results_version -> Int4,
     }
 }
 
@@ -73,6 +98,22 @@ diesel::table! {
         race_name -> Text,
         event_time -> Timestamp,
         created_at -> Timestamp,
+        circuit_id -> Nullable<Text>,
+        circuit_name -> Nullable<Text>,
+        sprint_time -> Nullable<Timestamp>,
+        first_practice_time -> Nullable<Timestamp>,
+        second_practice_time -> Nullable<Timestamp>,
+        third_practice_time -> Nullable<Timestamp>,
+        qualifying_time -> Nullable<Timestamp>,
+        calendar_version -> Int4,
+    }
+}
+
+diesel::table! {
+    rated_races (id) {
+        id -> Int4,
+        race_id -> Int4,
+        created_at -> Timestamp,
     }
 }
 
@@ -85,6 +126,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sprint_results (id) {
+        id -> Int4,
+        race_id -> Int4,
+        driver_id -> Text,
+        constructor_id -> Text,
+        position -> Int4,
+        position_text -> Text,
+        points -> Numeric,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     standings (id) {
         id -> Int4,
@@ -99,6 +153,9 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(driver_ratings -> drivers (driver_id));
+diesel::joinable!(driver_ratings -> races (race_id));
+diesel::joinable!(lap_flags -> races (race_id));
 diesel::joinable!(laptimes -> drivers (driver_id));
 diesel::joinable!(laptimes -> races (race_id));
 diesel::joinable!(pitstops -> drivers (driver_id));
@@ -107,17 +164,25 @@ diesel::joinable!(race_results -> constructors (constructor_id));
 diesel::joinable!(race_results -> drivers (driver_id));
 diesel::joinable!(race_results -> races (race_id));
 diesel::joinable!(races -> seasons (season));
+diesel::joinable!(rated_races -> races (race_id));
+diesel::joinable!(sprint_results -> constructors (constructor_id));
+diesel::joinable!(sprint_results -> drivers (driver_id));
+diesel::joinable!(sprint_results -> races (race_id));
 diesel::joinable!(standings -> constructors (constructor_id));
 diesel::joinable!(standings -> drivers (driver_id));
 diesel::joinable!(standings -> races (race));
 
 diesel::allow_tables_to_appear_in_same_query!(
     constructors,
+    driver_ratings,
     drivers,
+    lap_flags,
     laptimes,
     pitstops,
     race_results,
     races,
+    rated_races,
     seasons,
+    sprint_results,
     standings,
 );
@@ -1,7 +1,15 @@
-use crate::db::connection::Pool;
+use crate::analysis::fuel::FuelModel;
+use crate::analysis::records::RecordsCache;
+use crate::analysis::{ratings, simulation};
+use crate::clock::Clock;
+use crate::db::connection::{Pool, PooledConnection};
 use crate::db::db_models::{self, Season};
-use crate::queries::{RoundQuery, YearQuery, LapChartQuery};
-use axum::extract::Query;
+use crate::queries::{
+    ConstructorListQuery, DriverListQuery, FuelQuery, HeadToHeadQuery, LapChartQuery,
+    RaceStatsQuery, RaceTraceQuery, RatingsQuery, RecordsQuery, RescoreQuery, RoundQuery,
+    SimulationQuery, StandingsQuery, StandingsSource, YearQuery,
+};
+use axum::extract::{self, Query};
 use axum::response::IntoResponse;
 use axum::Extension;
 use axum::{http::StatusCode, Json};
@@ -9,6 +17,8 @@ use ergast_rust::api::{Path, URLParams};
 use ergast_rust::ergast::Ergast;
 use serde_json::Value;
 
+pub mod v1;
+
 // basic handler that responds with a static string
 pub async fn root() -> &'static str {
     "Hello, World"
@@ -20,20 +30,56 @@ pub async fn races_handler(
     year: Query<YearQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let season = db_models::Season::get(year.year, &mut conn);
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
 
     let result = db_models::Race::generate_response(&season, &mut conn).await;
     let value = serde_json::to_value(result).unwrap();
     Ok((StatusCode::OK, Json(value)))
 }
 
+pub async fn next_race_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    match db_models::Race::generate_next(clock.now(), &mut conn).await {
+        Some(race) => {
+            let value = serde_json::to_value(race).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        None => Err((StatusCode::NOT_FOUND, Json("race not found"))),
+    }
+}
+
+pub async fn last_race_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    match db_models::Race::generate_last(clock.now(), &mut conn).await {
+        Some(race) => {
+            let value = serde_json::to_value(race).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        None => Err((StatusCode::NOT_FOUND, Json("race not found"))),
+    }
+}
+
 // basic handler that responds with a static string
 pub async fn standings_handler(
     Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
     round: Query<RoundQuery>,
+    source: Query<StandingsQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let season = Season::get(round.year, &mut conn);
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
     let race = db_models::Race::get(&season, round.round, &mut conn);
 
     let race = match race {
@@ -43,7 +89,243 @@ pub async fn standings_handler(
         }
     };
 
-    let result = db_models::Standing::generate_response(&race, &mut conn).await;
+    let value = match source.source {
+        StandingsSource::Upstream => {
+            let result = db_models::Standing::generate_response(&race, &mut conn).await;
+            serde_json::to_value(result).unwrap()
+        }
+        StandingsSource::Local => {
+            let result =
+                crate::analysis::standings::generate_chart(&season, &race, clock.now(), &mut conn)
+                    .await;
+            match result {
+                Some(chart) => serde_json::to_value(chart).unwrap(),
+                None => return Err((StatusCode::BAD_REQUEST, Json("unsupported season"))),
+            }
+        }
+    };
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn standings_progression_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+
+    let result = db_models::Standing::generate_progression(&season, clock.now(), &mut conn).await;
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn computed_standings_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    round: Query<RoundQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
+    let result =
+        crate::analysis::standings::generate_response(&season, &race, clock.now(), &mut conn).await;
+    let result = match result {
+        Some(r) => r,
+        None => return Err((StatusCode::BAD_REQUEST, Json("unsupported season"))),
+    };
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn standings_check_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    round: Query<RoundQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
+    let result =
+        match crate::analysis::standings::check(&season, &race, clock.now(), &mut conn).await {
+            Some(r) => r,
+            None => return Err((StatusCode::BAD_REQUEST, Json("unsupported season"))),
+        };
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn championship_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+
+    // only seasons with a known points system can be calculated
+    let result =
+        match crate::analysis::championship::generate_response(&season, clock.now(), &mut conn)
+            .await
+        {
+            Some(r) => r,
+            None => {
+                return Err((StatusCode::BAD_REQUEST, Json("unsupported season")));
+            }
+        };
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn simulation_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+    query: Query<SimulationQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let iterations = query
+        .iterations
+        .unwrap_or(simulation::DEFAULT_ITERATIONS)
+        .clamp(1, simulation::MAX_ITERATIONS);
+
+    let result = match simulation::generate_response(
+        &season,
+        iterations,
+        query.seed,
+        clock.now(),
+        &mut conn,
+    )
+    .await
+    {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("unsupported season")));
+        }
+    };
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn records_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(cache): Extension<RecordsCache>,
+    query: Query<RecordsQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    // unknown filters are rejected before they reach the cache, which keeps one entry per filter
+    if let Some(season) = query.season {
+        if db_models::Season::find(season, &mut conn).is_none() {
+            return Err((StatusCode::NOT_FOUND, Json("season not found")));
+        }
+    }
+    if let Some(id) = &query.constructor {
+        if db_models::Constructor::find_by_id(id, &mut conn).is_none() {
+            return Err((StatusCode::NOT_FOUND, Json("constructor not found")));
+        }
+    }
+    match cache.get(&query, &mut conn) {
+        Ok(value) => Ok((StatusCode::OK, Json(value))),
+        Err(e) => {
+            println!("Error loading records: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
+pub async fn season_matrix_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    match crate::analysis::matrix::generate_response(&season, clock.now(), &mut conn).await {
+        Ok(Some(matrix)) => {
+            let value = serde_json::to_value(matrix).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Ok(None) => Err((StatusCode::BAD_REQUEST, Json("unsupported season"))),
+        Err(e) => {
+            println!("Error loading season matrix {}: {}", year.year, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
+pub async fn reliability_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+
+    let result =
+        crate::analysis::reliability::generate_response(&season, clock.now(), &mut conn).await;
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn rescore_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+    query: Query<RescoreQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(year.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+
+    let result = match crate::analysis::rescoring::generate_response(
+        &season,
+        &query.system,
+        query.fastest_lap,
+        query.best_results,
+        clock.now(),
+        &mut conn,
+    )
+    .await
+    {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("unknown points system")));
+        }
+    };
     let value = serde_json::to_value(result).unwrap();
     Ok((StatusCode::OK, Json(value)))
 }
@@ -53,7 +335,10 @@ pub async fn results_handler(
     round: Query<RoundQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let season = Season::get(round.year, &mut conn);
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
     let race = db_models::Race::get(&season, round.round, &mut conn);
     let race = match race {
         Some(r) => r,
@@ -68,9 +353,10 @@ pub async fn results_handler(
 
 pub async fn seasons_handler(
     Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let result = db_models::Season::generate_response(&mut conn).await;
+    let result = db_models::Season::generate_response(clock.now(), &mut conn).await;
 
     match result {
         Ok(seasons) => {
@@ -81,6 +367,25 @@ pub async fn seasons_handler(
     }
 }
 
+pub async fn season_get(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    year: Query<YearQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    match db_models::Season::generate_summary(year.year, clock.now(), &mut conn).await {
+        Ok(Some(summary)) => {
+            let value = serde_json::to_value(summary).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json("season not found"))),
+        Err(e) => {
+            println!("Error loading season {}: {}", year.year, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
 pub async fn seasons_post(Extension(pool): Extension<Pool>) -> impl IntoResponse {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
     let _ = crate::db::db_models::Season::post(&mut conn).await;
@@ -112,11 +417,17 @@ pub async fn laps_handler(
 
 pub async fn laps_chart_handler(
     Extension(pool): Extension<Pool>,
+    Extension(fuel): Extension<FuelModel>,
+    round: Query<RoundQuery>,
     query: Query<LapChartQuery>,
+    fuel_query: Query<FuelQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let season = Season::get(query.year, &mut conn);
-    let race = db_models::Race::get(&season, query.round, &mut conn);
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = db_models::Race::get(&season, round.round, &mut conn);
 
     let race = match race {
         Some(r) => r,
@@ -124,32 +435,260 @@ pub async fn laps_chart_handler(
             return Err((StatusCode::BAD_REQUEST, Json("error")));
         }
     };
-    let result = db_models::Laptime::generate_response(&race, query.exclude_pitstop, &mut conn).await;
+    let fuel_per_lap = fuel.per_lap(&fuel_query, race.circuit_id());
+    let result =
+        crate::analysis::lap_chart::generate_response(&race, &query, fuel_per_lap, &mut conn).await;
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn positions_chart_handler(
+    Extension(pool): Extension<Pool>,
+    round: Query<RoundQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
+    let result = db_models::Laptime::generate_position_response(&race, &mut conn).await;
     let value = serde_json::to_value(result).unwrap();
     Ok((StatusCode::OK, Json(value)))
 }
 
+pub async fn race_trace_handler(
+    Extension(pool): Extension<Pool>,
+    round: Query<RoundQuery>,
+    query: Query<RaceTraceQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
+    let result =
+        crate::analysis::race_trace::generate_response(&race, query.reference_pace, &mut conn)
+            .await;
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn stints_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(fuel): Extension<FuelModel>,
+    round: Query<RoundQuery>,
+    fuel_query: Query<FuelQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
+    let fuel_per_lap = fuel.per_lap(&fuel_query, race.circuit_id());
+    let result = crate::analysis::stints::generate_response(&race, fuel_per_lap, &mut conn).await;
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn race_stats_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    query: Query<RaceStatsQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(query.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let result = match query.round {
+        Some(round) => {
+            let race = match db_models::Race::get(&season, round, &mut conn) {
+                Some(r) => r,
+                None => {
+                    return Err((StatusCode::BAD_REQUEST, Json("error")));
+                }
+            };
+            crate::analysis::overtakes::generate_race_response(&season, &race, &mut conn).await
+        }
+        None => {
+            crate::analysis::overtakes::generate_season_response(&season, clock.now(), &mut conn)
+                .await
+        }
+    };
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
+pub async fn race_pace_handler(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    query: Query<RaceStatsQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(query.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let value = match query.round {
+        Some(round) => {
+            let race = match db_models::Race::get(&season, round, &mut conn) {
+                Some(r) => r,
+                None => {
+                    return Err((StatusCode::BAD_REQUEST, Json("error")));
+                }
+            };
+            let result =
+                crate::analysis::pace::generate_race_response(&season, &race, &mut conn).await;
+            serde_json::to_value(result).unwrap()
+        }
+        None => {
+            let result =
+                crate::analysis::pace::generate_season_response(&season, clock.now(), &mut conn)
+                    .await;
+            serde_json::to_value(result).unwrap()
+        }
+    };
+    Ok((StatusCode::OK, Json(value)))
+}
+
 pub async fn pitstops_handler(
     Extension(pool): Extension<Pool>,
     round: Query<RoundQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let season = Season::get(round.year, &mut conn);
-    let race = db_models::Race::get(&season, round.round, &mut conn).unwrap();
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
     let result = db_models::Pitstop::generate_response(&race, &mut conn).await;
     let value = serde_json::to_value(result).unwrap();
     Ok((StatusCode::OK, Json(value)))
 }
 
+pub async fn pit_analysis_handler(
+    Extension(pool): Extension<Pool>,
+    round: Query<RoundQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let season = match Season::find(round.year, &mut conn) {
+        Some(s) => s,
+        None => return Err((StatusCode::NOT_FOUND, Json("season not found"))),
+    };
+    let race = match db_models::Race::get(&season, round.round, &mut conn) {
+        Some(r) => r,
+        None => {
+            return Err((StatusCode::BAD_REQUEST, Json("error")));
+        }
+    };
+    let result = crate::analysis::pit_analysis::generate_response(&race, &mut conn).await;
+    let value = serde_json::to_value(result).unwrap();
+    Ok((StatusCode::OK, Json(value)))
+}
+
 pub async fn drivers_get(
     Extension(pool): Extension<Pool>,
+    query: Query<DriverListQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let result = db_models::Driver::generate_response(&mut conn);
+    let result = db_models::Driver::generate_response(&query, &mut conn);
     let value = serde_json::to_value(result).unwrap();
     Ok((StatusCode::OK, Json(value)))
 }
 
+pub async fn driver_get(
+    Extension(pool): Extension<Pool>,
+    extract::Path(id): extract::Path<String>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    match db_models::Driver::generate_profile(&id, &mut conn) {
+        Ok(Some(profile)) => {
+            let value = serde_json::to_value(profile).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json("driver not found"))),
+        Err(e) => {
+            println!("Error loading driver profile {}: {}", id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
+// rate the races fetched since the last request before answering.
+fn update_ratings(conn: &mut PooledConnection) -> Result<(), (StatusCode, Json<&'static str>)> {
+    match ratings::update(conn) {
+        Ok(n) if n > 0 => {
+            println!("Rated {} races", n);
+            Ok(())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            println!("Error updating ratings: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
+pub async fn ratings_handler(
+    Extension(pool): Extension<Pool>,
+    query: Query<RatingsQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    update_ratings(&mut conn)?;
+    match ratings::leaderboard(&query, &mut conn) {
+        Ok(page) => {
+            let value = serde_json::to_value(page).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Err(e) => {
+            println!("Error loading ratings: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
+pub async fn driver_ratings_handler(
+    Extension(pool): Extension<Pool>,
+    extract::Path(id): extract::Path<String>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    update_ratings(&mut conn)?;
+    match ratings::history(&id, &mut conn) {
+        Ok(Some(history)) => {
+            let value = serde_json::to_value(history).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json("driver not found"))),
+        Err(e) => {
+            println!("Error loading rating history {}: {}", id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
 pub async fn drivers_post(Extension(pool): Extension<Pool>) -> impl IntoResponse {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
     let _ = crate::db::db_models::Driver::post(&mut conn).await;
@@ -158,15 +697,62 @@ pub async fn drivers_post(Extension(pool): Extension<Pool>) -> impl IntoResponse
 
 pub async fn constructors_get(
     Extension(pool): Extension<Pool>,
+    query: Query<ConstructorListQuery>,
 ) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
-    let result = db_models::Constructor::generate_response(&mut conn);
+    let result = db_models::Constructor::generate_response(&query, &mut conn);
     let value = serde_json::to_value(result).unwrap();
     Ok((StatusCode::OK, Json(value)))
 }
 
+pub async fn constructor_get(
+    Extension(pool): Extension<Pool>,
+    Extension(clock): Extension<Clock>,
+    extract::Path(id): extract::Path<String>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    match db_models::Constructor::generate_profile(&id, clock.now(), &mut conn).await {
+        Ok(Some(profile)) => {
+            let value = serde_json::to_value(profile).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json("constructor not found"))),
+        Err(e) => {
+            println!("Error loading constructor profile {}: {}", id, e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
+
 pub async fn constructors_post(Extension(pool): Extension<Pool>) -> impl IntoResponse {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
     let _ = crate::db::db_models::Constructor::post(&mut conn).await;
     (StatusCode::OK, Json("ok"))
 }
+
+pub async fn head_to_head_handler(
+    Extension(pool): Extension<Pool>,
+    query: Query<HeadToHeadQuery>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)> {
+    if query.driver_a == query.driver_b {
+        return Err((StatusCode::BAD_REQUEST, Json("same driver")));
+    }
+    let mut conn = pool.get().expect("Failed to get DB connection from pool");
+    let result = crate::analysis::head_to_head::generate_response(
+        &query.driver_a,
+        &query.driver_b,
+        query.season,
+        &mut conn,
+    );
+    match result {
+        Ok(Some(h2h)) => {
+            let value = serde_json::to_value(h2h).unwrap();
+            Ok((StatusCode::OK, Json(value)))
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, Json("driver not found"))),
+        Err(e) => {
+            println!("Error comparing drivers: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json("error")))
+        }
+    }
+}
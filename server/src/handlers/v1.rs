@@ -0,0 +1,251 @@
+//! Versioned, resource-oriented routes mounted under `/api/v1`.
+//!
+//! Handlers here only translate path segments into the query structs used by
+//! the legacy flat routes and delegate to them, so both trees serve the same
+//! data until a v1 response shape needs to diverge.
+use crate::analysis::fuel::FuelModel;
+use crate::clock::Clock;
+use crate::db::connection::Pool;
+use crate::queries::{
+    FuelQuery, LapChartQuery, RaceStatsQuery, RaceTraceQuery, RescoreQuery, RoundQuery,
+    SimulationQuery, StandingsQuery, YearQuery,
+};
+use axum::extract::{Path, Query};
+use axum::routing::get;
+use axum::{http::StatusCode, Extension, Json, Router};
+use serde_json::Value;
+
+type HandlerResult = Result<(StatusCode, Json<Value>), (StatusCode, Json<&'static str>)>;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/seasons",
+            get(super::seasons_handler).post(super::seasons_post),
+        )
+        .route("/seasons/:year", get(season))
+        .route("/seasons/:year/races", get(races))
+        .route("/seasons/:year/standings", get(standings_progression))
+        .route("/seasons/:year/championship", get(championship))
+        .route("/seasons/:year/matrix", get(season_matrix))
+        .route("/seasons/:year/reliability", get(reliability))
+        .route("/seasons/:year/rescore", get(rescore))
+        .route("/seasons/:year/simulation", get(simulation))
+        .route("/seasons/:year/stats", get(season_stats))
+        .route("/seasons/:year/pace", get(season_pace))
+        .route("/races/next", get(super::next_race_handler))
+        .route("/races/last", get(super::last_race_handler))
+        .route("/seasons/:year/races/:round/results", get(results))
+        .route("/seasons/:year/races/:round/standings", get(standings))
+        .route(
+            "/seasons/:year/races/:round/standings/computed",
+            get(computed_standings),
+        )
+        .route(
+            "/seasons/:year/races/:round/standings/check",
+            get(standings_check),
+        )
+        .route("/seasons/:year/races/:round/laps", get(laps))
+        .route("/seasons/:year/races/:round/laps/chart", get(laps_chart))
+        .route(
+            "/seasons/:year/races/:round/positions/chart",
+            get(positions_chart),
+        )
+        .route("/seasons/:year/races/:round/trace", get(race_trace))
+        .route("/seasons/:year/races/:round/stints", get(stints))
+        .route("/seasons/:year/races/:round/stats", get(race_stats))
+        .route("/seasons/:year/races/:round/pace", get(race_pace))
+        .route("/seasons/:year/races/:round/pitstops", get(pitstops))
+        .route(
+            "/seasons/:year/races/:round/pitstops/analysis",
+            get(pit_analysis),
+        )
+        .route(
+            "/drivers",
+            get(super::drivers_get).post(super::drivers_post),
+        )
+        .route("/drivers/:id", get(super::driver_get))
+        .route("/drivers/:id/ratings", get(super::driver_ratings_handler))
+        .route("/ratings", get(super::ratings_handler))
+        .route("/records", get(super::records_handler))
+        .route("/head-to-head", get(super::head_to_head_handler))
+        .route(
+            "/constructors",
+            get(super::constructors_get).post(super::constructors_post),
+        )
+        .route("/constructors/:id", get(super::constructor_get))
+}
+
+async fn season(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::season_get(pool, clock, Query(YearQuery { year })).await
+}
+
+async fn races(pool: Extension<Pool>, Path(year): Path<i32>) -> HandlerResult {
+    super::races_handler(pool, Query(YearQuery { year })).await
+}
+
+async fn standings_progression(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::standings_progression_handler(pool, clock, Query(YearQuery { year })).await
+}
+
+async fn championship(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::championship_handler(pool, clock, Query(YearQuery { year })).await
+}
+
+async fn season_matrix(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::season_matrix_handler(pool, clock, Query(YearQuery { year })).await
+}
+
+async fn reliability(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::reliability_handler(pool, clock, Query(YearQuery { year })).await
+}
+
+async fn rescore(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+    query: Query<RescoreQuery>,
+) -> HandlerResult {
+    super::rescore_handler(pool, clock, Query(YearQuery { year }), query).await
+}
+
+async fn simulation(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+    query: Query<SimulationQuery>,
+) -> HandlerResult {
+    super::simulation_handler(pool, clock, Query(YearQuery { year }), query).await
+}
+
+async fn season_stats(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::race_stats_handler(pool, clock, Query(RaceStatsQuery { year, round: None })).await
+}
+
+async fn season_pace(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(year): Path<i32>,
+) -> HandlerResult {
+    super::race_pace_handler(pool, clock, Query(RaceStatsQuery { year, round: None })).await
+}
+
+async fn results(pool: Extension<Pool>, Path(round): Path<RoundQuery>) -> HandlerResult {
+    super::results_handler(pool, Query(round)).await
+}
+
+async fn standings(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(round): Path<RoundQuery>,
+    source: Query<StandingsQuery>,
+) -> HandlerResult {
+    super::standings_handler(pool, clock, Query(round), source).await
+}
+
+async fn computed_standings(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(round): Path<RoundQuery>,
+) -> HandlerResult {
+    super::computed_standings_handler(pool, clock, Query(round)).await
+}
+
+async fn standings_check(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(round): Path<RoundQuery>,
+) -> HandlerResult {
+    super::standings_check_handler(pool, clock, Query(round)).await
+}
+
+async fn laps(Path(round): Path<RoundQuery>) -> HandlerResult {
+    super::laps_handler(Query(round)).await
+}
+
+async fn laps_chart(
+    pool: Extension<Pool>,
+    fuel: Extension<FuelModel>,
+    Path(round): Path<RoundQuery>,
+    query: Query<LapChartQuery>,
+    fuel_query: Query<FuelQuery>,
+) -> HandlerResult {
+    super::laps_chart_handler(pool, fuel, Query(round), query, fuel_query).await
+}
+
+async fn positions_chart(pool: Extension<Pool>, Path(round): Path<RoundQuery>) -> HandlerResult {
+    super::positions_chart_handler(pool, Query(round)).await
+}
+
+async fn race_trace(
+    pool: Extension<Pool>,
+    Path(round): Path<RoundQuery>,
+    query: Query<RaceTraceQuery>,
+) -> HandlerResult {
+    super::race_trace_handler(pool, Query(round), query).await
+}
+
+async fn stints(
+    pool: Extension<Pool>,
+    fuel: Extension<FuelModel>,
+    Path(round): Path<RoundQuery>,
+    fuel_query: Query<FuelQuery>,
+) -> HandlerResult {
+    super::stints_handler(pool, fuel, Query(round), fuel_query).await
+}
+
+async fn race_stats(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(round): Path<RoundQuery>,
+) -> HandlerResult {
+    let query = RaceStatsQuery {
+        year: round.year,
+        round: Some(round.round),
+    };
+    super::race_stats_handler(pool, clock, Query(query)).await
+}
+
+async fn race_pace(
+    pool: Extension<Pool>,
+    clock: Extension<Clock>,
+    Path(round): Path<RoundQuery>,
+) -> HandlerResult {
+    let query = RaceStatsQuery {
+        year: round.year,
+        round: Some(round.round),
+    };
+    super::race_pace_handler(pool, clock, Query(query)).await
+}
+
+async fn pitstops(pool: Extension<Pool>, Path(round): Path<RoundQuery>) -> HandlerResult {
+    super::pitstops_handler(pool, Query(round)).await
+}
+
+async fn pit_analysis(pool: Extension<Pool>, Path(round): Path<RoundQuery>) -> HandlerResult {
+    super::pit_analysis_handler(pool, Query(round)).await
+}
@@ -1,4 +1,7 @@
+use analysis::fuel::FuelModel;
+use analysis::records::RecordsCache;
 use axum::{routing::get, Extension, Router};
+use clock::Clock;
 use db::{
     connection::PooledConnection,
     db_models::{Constructor, Driver, Season},
@@ -13,8 +16,16 @@ use handlers::{
 };
 
 use crate::handlers::{
-    constructors_get, constructors_post, drivers_get, drivers_post, pitstops_handler, seasons_post,
+    championship_handler, computed_standings_handler, constructor_get, constructors_get,
+    constructors_post, driver_get, driver_ratings_handler, drivers_get, drivers_post,
+    head_to_head_handler, last_race_handler, next_race_handler, pit_analysis_handler,
+    pitstops_handler, positions_chart_handler, race_pace_handler, race_stats_handler,
+    race_trace_handler, ratings_handler, records_handler, reliability_handler, rescore_handler,
+    season_get, season_matrix_handler, seasons_post, simulation_handler, standings_check_handler,
+    standings_progression_handler, stints_handler,
 };
+mod analysis;
+mod clock;
 mod db;
 mod models;
 mod queries;
@@ -38,23 +49,50 @@ async fn main() {
     let mut conn = pool.get().expect("Failed to get DB connection from pool");
     check_and_create_tables(&mut conn).await;
 
-    // build our application with a route
+    // build our application with the legacy flat routes and the versioned api tree
     let app = Router::new()
         .route("/", get(root))
         .route("/standings", get(standings_handler))
+        .route("/standings/progression", get(standings_progression_handler))
+        .route("/standings/computed", get(computed_standings_handler))
+        .route("/standings/check", get(standings_check_handler))
+        .route("/championship", get(championship_handler))
+        .route("/rescore", get(rescore_handler))
+        .route("/reliability", get(reliability_handler))
+        .route("/simulation", get(simulation_handler))
         .route("/seasons", get(seasons_handler).post(seasons_post))
+        .route("/seasons/summary", get(season_get))
+        .route("/seasons/matrix", get(season_matrix_handler))
         .route("/races", get(races_handler))
+        .route("/races/next", get(next_race_handler))
+        .route("/races/last", get(last_race_handler))
         .route("/results", get(results_handler))
         .route("/laps", get(laps_handler))
         .route("/laps-chart", get(laps_chart_handler))
+        .route("/positions-chart", get(positions_chart_handler))
+        .route("/race-trace", get(race_trace_handler))
+        .route("/race-stats", get(race_stats_handler))
+        .route("/race-pace", get(race_pace_handler))
+        .route("/stints", get(stints_handler))
         .route("/pitstops", get(pitstops_handler))
+        .route("/pitstops/analysis", get(pit_analysis_handler))
         .route("/drivers", get(drivers_get).post(drivers_post))
+        .route("/drivers/:id", get(driver_get))
         .route(
             "/constructors",
             get(constructors_get).post(constructors_post),
         )
+        .route("/constructors/:id", get(constructor_get))
+        .route("/ratings", get(ratings_handler))
+        .route("/records", get(records_handler))
+        .route("/ratings/:id", get(driver_ratings_handler))
+        .route("/head-to-head", get(head_to_head_handler))
+        .nest("/api/v1", handlers::v1::router())
         .layer(cors)
-        .layer(Extension(pool));
+        .layer(Extension(pool))
+        .layer(Extension(FuelModel::from_env()))
+        .layer(Extension(RecordsCache::default()))
+        .layer(Extension(Clock::from_env()));
 
     // run our app with hyper, listening globally on port 3000
     let port = "0.0.0.0:3000";
@@ -2,8 +2,7 @@ use chrono::NaiveDate;
 use ergast_rust::models::{MRData, RaceTable};
 use serde::{Deserialize, Serialize};
 
-use crate::db::connection::PooledConnection;
-use crate::db::db_models::{Constructor, RaceResult};
+use crate::db::db_models::{Constructor, Driver};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RaceResponse {
@@ -14,6 +13,56 @@ pub struct RaceResponse {
     pub date: String,
 }
 
+/// RaceEventResponse is the race returned by /races/next and /races/last.
+/// `seconds_until` is negative once the race has started, so for the last race it is
+/// the time since the start.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RaceEventResponse {
+    pub season: i32,
+    pub round: i32,
+    pub race_name: String,
+    pub circuit_id: Option<String>,
+    pub circuit_name: Option<String>,
+    pub date: String,
+    pub seconds_until: i64,
+    pub sessions: RaceSessions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub podium: Option<Vec<PodiumEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fastest_lap: Option<FastestLapEntry>,
+}
+
+/// start of each weekend session, None if the race does not have it or it is not known.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RaceSessions {
+    pub first_practice: Option<String>,
+    pub second_practice: Option<String>,
+    pub third_practice: Option<String>,
+    pub qualifying: Option<String>,
+    pub sprint: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PodiumEntry {
+    pub position: i32,
+    pub driver_id: String,
+    pub code: String,
+    pub given_name: String,
+    pub family_name: String,
+    pub constructor_id: String,
+    pub constructor: String,
+    pub points: f64,
+    pub time_millis: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct FastestLapEntry {
+    pub driver_id: String,
+    pub code: String,
+    pub constructor_id: String,
+    pub lap_time: Option<f64>, // seconds, if the laps of the race are stored
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RaceResultResponse {
     pub id: i32,
@@ -27,10 +76,47 @@ pub struct RaceResultResponse {
     pub constructor: String,
 }
 
+/// SeasonResponse is an overview of a season from the stored data. the leaders are the
+/// champions once the season is `finished`. the drivers' leader comes from the stored
+/// standings after the last completed round, the constructors' one from the stored results
+/// when they cover every completed round of a season since 1991. either is None otherwise.
 #[derive(Deserialize, Serialize, Debug)]
 pub struct SeasonResponse {
     pub season: i32,
     pub url: String,
+    pub rounds: i64,
+    pub completed_rounds: i64,
+    pub finished: bool,
+    pub driver_leader: Option<SeasonLeader>,
+    pub constructor_leader: Option<SeasonLeader>,
+    pub winners: Vec<RaceWinner>,
+    pub coverage: SeasonCoverage,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SeasonLeader {
+    pub id: String, // driver or constructor id
+    pub points: f64,
+    pub wins: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RaceWinner {
+    pub round: i32,
+    pub race_name: String,
+    pub driver_id: String,
+    pub code: String,
+    pub constructor_id: String,
+}
+
+/// which data is stored: the calendar, and each kind of data for every completed round.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SeasonCoverage {
+    pub calendar: bool,
+    pub results: bool,
+    pub laps: bool,
+    pub pitstops: bool,
+    pub standings: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -42,7 +128,7 @@ pub struct LapResponse {
 }
 
 pub fn convert_to_lap_responses(data: MRData<RaceTable>) -> Vec<LapResponse> {
-    let laps = data.table.races.get(0).unwrap().laps.as_ref().unwrap();
+    let laps = data.table.races.first().unwrap().laps.as_ref().unwrap();
     let mut vec = Vec::new();
     for lap in laps {
         for timing in &lap.timings {
@@ -66,6 +152,273 @@ pub struct ChartResponse<T, U> {
     pub color: Option<Vec<String>>,
 }
 
+/// SimulationResponse provides championship probabilities of drivers and constructors from
+/// simulating the rest of the season.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SimulationResponse {
+    pub season: i32,
+    pub iterations: usize,
+    pub seed: u64, // pass it back to reproduce the result
+    pub remaining_rounds: usize,
+    pub drivers: Vec<SimulatedEntry>,
+    pub constructors: Vec<SimulatedEntry>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SimulatedEntry {
+    pub id: String,
+    pub name: String, // driver code or constructor name
+    pub color: String,
+    pub expected_points: f64,
+    pub title_probability: f64,
+    pub expected_position: f64,
+    pub position_distribution: Vec<f64>, // probability of each final position, from 1st
+}
+
+/// ComputedStandingsResponse provides the driver and constructor standings after a round,
+/// computed from the stored race and sprint results.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ComputedStandingsResponse {
+    pub season: i32,
+    pub round: i32,
+    pub drivers: Vec<ComputedStanding>,
+    pub constructors: Vec<ComputedStanding>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ComputedStanding {
+    pub id: String,
+    pub name: String, // driver code or constructor name
+    pub color: String,
+    pub position: i32,
+    pub points: f64,
+    pub wins: i32,
+}
+
+/// StandingsCheckResponse lists the drivers whose computed standing differs from Ergast API's.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StandingsCheckResponse {
+    pub season: i32,
+    pub round: i32,
+    pub consistent: bool,
+    pub drivers: usize,
+    pub mismatches: Vec<StandingMismatch>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StandingMismatch {
+    pub driver_id: String,
+    pub points: Option<f64>, // None if the driver has no stored result
+    pub upstream_points: Option<i32>,
+    pub position: Option<i32>,
+    pub upstream_position: Option<i32>,
+}
+
+/// ChampionshipResponse tells which drivers can still win the title and when it can be decided.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ChampionshipResponse {
+    pub season: i32,
+    pub completed_rounds: usize,
+    pub remaining_rounds: usize,
+    pub remaining_sprints: usize,
+    pub max_remaining_points: i32,
+    pub drivers: Vec<DriverTitleChances>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverTitleChances {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub position: i32,
+    pub points: i32,
+    pub max_points: i32,
+    pub alive: bool,
+    pub clinched: bool,
+    pub clinch_finish: Option<i32>, // worst finish in the next race which clinches the title
+    pub earliest_clinch_round: Option<i32>,
+}
+
+/// SeasonMatrixResponse provides every driver's result in every round of a season, with the
+/// drivers in championship order. a cell is None when the driver was not entered.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SeasonMatrixResponse {
+    pub season: i32,
+    pub rounds: Vec<MatrixRound>,
+    pub drivers: Vec<MatrixRow>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MatrixRound {
+    pub round: i32,
+    pub race_name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MatrixRow {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub position: i32,
+    pub points: f64,                    // championship points, sprints included
+    pub cells: Vec<Option<MatrixCell>>, // indexed like `rounds`
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MatrixCell {
+    pub position: Option<i32>, // None if not classified
+    pub position_text: String,
+    pub points: f64,      // grand prix points
+    pub category: String, // classified, retired, disqualified, not_classified, withdrawn or did_not_qualify
+}
+
+/// RecordsResponse provides records and streaks over the stored results, all time or
+/// limited to a season or a constructor.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RecordsResponse {
+    pub season: Option<i32>,
+    pub constructor: Option<String>,
+    pub races: usize,
+    pub most_wins: Vec<CountRecord>,
+    pub win_streaks: Vec<StreakRecord>,
+    pub points_streaks: Vec<StreakRecord>,
+    pub youngest_winners: Vec<AgeRecord>,
+    pub oldest_winners: Vec<AgeRecord>,
+    pub biggest_margins: Vec<MarginRecord>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RaceRef {
+    pub season: i32,
+    pub round: i32,
+    pub race_name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CountRecord {
+    pub driver_id: String,
+    pub name: String,
+    pub count: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StreakRecord {
+    pub driver_id: String,
+    pub name: String,
+    pub length: usize,
+    pub from: RaceRef,
+    pub to: RaceRef,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AgeRecord {
+    pub driver_id: String,
+    pub name: String,
+    pub age_days: i64,
+    pub age_years: f64,
+    pub race: RaceRef,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MarginRecord {
+    pub driver_id: String,
+    pub name: String,
+    pub runner_up: String,
+    pub margin_seconds: f64,
+    pub race: RaceRef,
+}
+
+/// ReliabilityResponse provides finish rates and retirements of each constructor in a season.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ReliabilityResponse {
+    pub season: i32,
+    pub races: usize,
+    pub constructors: Vec<ConstructorReliability>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ConstructorReliability {
+    pub constructor_id: String,
+    pub name: String,
+    pub color: String,
+    pub starts: i32,
+    pub classified: i32,
+    pub finish_rate: f64, // classified over starts
+    pub retirements: i32,
+    pub mechanical: i32,
+    pub accidents: i32,
+    pub other: i32, // disqualifications and the like
+    pub laps_completed: i32,
+    pub laps_ratio: f64, // laps completed over the race distance of every start
+    pub failures: Vec<StatusCount>, // most common statuses other than a finish
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StatusCount {
+    pub status: String,
+    pub count: i32,
+}
+
+/// RescoreResponse provides a season's driver and constructor standings under another
+/// points system, next to the real ones.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RescoreResponse {
+    pub season: i32,
+    pub system: String,
+    pub fastest_lap: bool,
+    pub best_results: Option<usize>,
+    pub races: usize,
+    pub drivers: Vec<RescoredEntry>,
+    pub constructors: Vec<RescoredEntry>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RescoredEntry {
+    pub id: String,
+    pub name: String, // driver code or constructor name
+    pub color: String,
+    pub points: i32,
+    pub dropped_points: i32,
+    pub position: i32,
+    pub real_points: f64,
+    pub real_position: i32,
+    pub position_change: i32, // positive when the entry ranks higher under the system
+}
+
+/// ProgressionResponse provides data for a championship progression line chart.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ProgressionResponse {
+    pub season: i32,
+    pub rounds: Vec<i32>, // completed rounds
+    pub race_names: Vec<String>,
+    pub drivers: Vec<DriverProgression>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverProgression {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub rounds: Vec<i32>,
+    pub points: Vec<i32>,    // cumulative points after the round
+    pub positions: Vec<i32>, // championship position after the round
+}
+
+/// LapChartResponse provides lap chart data with the filters that hid laps from it.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LapChartResponse {
+    pub fuel_per_lap: Option<f64>,
+    pub filters: Vec<LapChartFilter>,
+    pub drivers: Vec<LapLineChartData>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LapChartFilter {
+    pub name: String,
+    pub description: String,
+    pub hidden_laps: usize,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct LapLineChartData {
     pub driver_id: String,
@@ -73,19 +426,125 @@ pub struct LapLineChartData {
     pub laps: Vec<i32>,
     pub laptime: Vec<f64>,
     pub color: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub neutralized_laps: Option<Vec<i32>>,
 }
 
-impl LapLineChartData {
-    pub fn new(driver_id: String, race_result: RaceResult, conn: &mut PooledConnection) -> Self {
-        let constructor = Constructor::get_by_id(&race_result.constructor_id, conn);
-        Self {
-            driver_id: driver_id.clone(),
-            position: race_result.position,
-            laps: Vec::new(),
-            laptime: Vec::new(),
-            color: constructor.team_color().to_string(),
-        }
-    }
+/// PositionChartData provides a driver's trace for a race position chart. lap 0 is the grid.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PositionChartData {
+    pub driver_id: String,
+    pub code: String,
+    pub position: i32, // result position
+    pub laps: Vec<i32>,
+    pub positions: Vec<i32>,
+    pub pit_laps: Vec<i32>,
+    pub color: String,
+}
+
+/// RaceTraceResponse provides data for a race trace chart. gaps are in seconds.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RaceTraceResponse {
+    pub reference_pace: f64, // seconds per lap
+    pub drivers: Vec<DriverTrace>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverTrace {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub laps: Vec<i32>,
+    pub gap_to_leader: Vec<f64>,
+    pub gap_to_reference: Vec<f64>,
+    pub pit_laps: Vec<i32>,
+}
+
+/// StintResponse provides the stints of a driver between pit stops.
+/// pace values are in seconds and exclude lap 1, in-laps and out-laps.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct StintResponse {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub stints: Vec<Stint>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Stint {
+    pub stint: i32,
+    pub start_lap: i32,
+    pub end_lap: i32,
+    pub laps: i32,
+    pub clean_laps: usize,
+    pub mean_pace: Option<f64>,
+    pub median_pace: Option<f64>,
+    pub degradation: Option<f64>, // seconds lost per lap
+}
+
+/// RaceStatsResponse provides overtaking statistics of a race, or of a season when `round` is None.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RaceStatsResponse {
+    pub season: i32,
+    pub round: Option<i32>,
+    pub races: usize,
+    pub total_passes: i32,
+    pub drivers: Vec<DriverRaceStats>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverRaceStats {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub races: usize,
+    pub grid: Option<i32>,   // only for a single race
+    pub finish: Option<i32>, // only for a single race
+    pub places_gained: i32,  // grid to finish
+    pub first_lap_gain: i32,
+    pub passes_made: i32, // on-track passes, not explained by pit stops
+    pub passes_lost: i32,
+    pub laps_led: i32,
+}
+
+/// RacePaceResponse ranks the drivers of a race by their median clean lap. times are in seconds.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RacePaceResponse {
+    pub season: i32,
+    pub round: i32,
+    pub drivers: Vec<DriverPace>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverPace {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub rank: i32,
+    pub clean_laps: usize,
+    pub median: f64,
+    pub std_dev: f64,
+    pub iqr: f64,
+    pub percent_off: f64, // off the fastest median of the race
+}
+
+/// SeasonPaceResponse averages the race pace rankings over the completed races of a season.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SeasonPaceResponse {
+    pub season: i32,
+    pub races: usize,
+    pub drivers: Vec<DriverSeasonPace>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverSeasonPace {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub races: usize, // races with enough clean laps to be ranked
+    pub average_percent_off: f64,
+    pub consistency_index: f64, // mean std dev over median in percent, lower is more consistent
+    pub fastest_races: usize,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -96,6 +555,51 @@ pub struct PitstopResponse {
     pub stop: i32,
 }
 
+/// PitAnalysisResponse provides the time lost in each pit stop, the strategy of each
+/// driver and the undercuts and overcuts of the race. times are in seconds.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PitAnalysisResponse {
+    pub stops: Vec<PitStopLoss>,
+    pub strategies: Vec<DriverStrategy>,
+    pub battles: Vec<PitBattle>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PitStopLoss {
+    pub driver_id: String,
+    pub stop: i32,
+    pub lap: i32,
+    pub duration: f64, // time in the pit lane, already part of the in-lap and out-lap
+    pub in_lap_loss: Option<f64>,
+    pub out_lap_loss: Option<f64>,
+    pub lap_loss: Option<f64>, // in-lap and out-lap loss, the pit lane time included
+    pub position_before: Option<i32>,
+    pub position_after: Option<i32>, // after the out-lap
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverStrategy {
+    pub driver_id: String,
+    pub code: String,
+    pub color: String,
+    pub stops: usize,
+    pub stop_laps: Vec<i32>,
+    pub grid: i32,
+    pub finish: i32,
+}
+
+/// the driver who stopped first got ahead (undercut), or the one who stayed out did (overcut).
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PitBattle {
+    pub kind: String,
+    pub winner: String,
+    pub loser: String,
+    pub first_stop_lap: i32,
+    pub second_stop_lap: i32,
+    pub gap_before: f64,
+    pub gap_after: f64,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct DriverResponse {
     pub id: String,
@@ -107,6 +611,60 @@ pub struct DriverResponse {
     pub nationality: String,
 }
 
+impl From<Driver> for DriverResponse {
+    fn from(d: Driver) -> Self {
+        Self {
+            id: d.id,
+            permanent_number: d.permanent_number,
+            code: d.code,
+            given_name: d.given_name,
+            family_name: d.family_name,
+            date_of_birth: d.date_of_birth,
+            nationality: d.nationality,
+        }
+    }
+}
+
+/// DriverProfileResponse provides a driver with career statistics aggregated from stored results.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverProfileResponse {
+    #[serde(flatten)]
+    pub driver: DriverResponse,
+    pub career: DriverCareerStats,
+    pub championships: Vec<ChampionshipPosition>,
+    pub teams: Vec<DriverTeam>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverCareerStats {
+    pub starts: i64,
+    pub wins: i64,
+    pub podiums: i64,
+    pub points: f64, // grand prix and sprint points
+    pub dnfs: i64,
+    pub best_finish: Option<i32>,
+}
+
+/// standing of the driver after the latest stored round of a season.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ChampionshipPosition {
+    pub season: i32,
+    pub round: i32,
+    pub is_final: bool, // true if the round is the last race of the season
+    pub position: i32,
+    pub points: i32,
+    pub wins: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DriverTeam {
+    pub constructor_id: String,
+    pub name: String,
+    pub first_season: i32,
+    pub last_season: i32,
+    pub starts: i64,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct ConstructorResponse {
     pub id: String,
@@ -114,3 +672,120 @@ pub struct ConstructorResponse {
     pub name: String,
     pub nationality: String,
 }
+
+impl From<Constructor> for ConstructorResponse {
+    fn from(c: Constructor) -> Self {
+        Self {
+            id: c.id,
+            url: c.url,
+            name: c.name,
+            nationality: c.nationality,
+        }
+    }
+}
+
+/// ConstructorProfileResponse provides a constructor with its season-by-season results.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ConstructorProfileResponse {
+    #[serde(flatten)]
+    pub constructor: ConstructorResponse,
+    pub color: String,
+    pub seasons: Vec<ConstructorSeason>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ConstructorSeason {
+    pub season: i32,
+    pub races: i64,
+    pub drivers: Vec<String>,
+    pub points: f64, // grand prix and sprint points
+    pub wins: i64,
+    pub podiums: i64,
+    pub best_finish: Option<i32>,
+    pub starts: i64,
+    pub dnfs: i64,
+    pub dnf_rate: Option<f64>,
+    // from the computed standings, so only since 1991
+    pub championship_position: Option<i32>,
+}
+
+/// Envelope for paginated list responses.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PageResponse<T> {
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub items: Vec<T>,
+}
+
+/// RatingEntry is a driver's place on the Elo leaderboard.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RatingEntry {
+    pub rank: i32,
+    pub driver_id: String,
+    pub code: Option<String>,
+    pub given_name: String,
+    pub family_name: String,
+    pub rating: f64,
+    pub peak_rating: f64,
+    pub races: i32,
+    pub last_season: i32,
+    pub last_round: i32,
+}
+
+/// RatingHistoryResponse provides a driver's Elo rating after each of their races.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RatingHistoryResponse {
+    pub driver_id: String,
+    pub rating: f64,
+    pub peak_rating: f64,
+    pub history: Vec<RatingPoint>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RatingPoint {
+    pub season: i32,
+    pub round: i32,
+    pub race_name: String,
+    pub rating: f64,
+    pub change: f64,
+}
+
+/// HeadToHeadResponse compares two drivers in the races where they were teammates.
+/// lap deltas are driver_a minus driver_b in seconds, so negative means driver_a was faster.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct HeadToHeadResponse {
+    pub driver_a: String,
+    pub driver_b: String,
+    pub season: Option<i32>,
+    pub summary: HeadToHeadSummary,
+    pub races: Vec<HeadToHeadRace>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct HeadToHeadSummary {
+    pub races: usize,
+    pub ahead_a: usize,
+    pub ahead_b: usize,
+    pub ties: usize, // races where both finished in the same position
+    pub points_a: f64,
+    pub points_b: f64,
+    pub races_with_laps: usize,
+    pub lap_delta_median: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct HeadToHeadRace {
+    pub season: i32,
+    pub round: i32,
+    pub race_name: String,
+    pub constructor_id: String,
+    pub position_a: i32,
+    pub position_text_a: String,
+    pub points_a: f64,
+    pub position_b: i32,
+    pub position_text_b: String,
+    pub points_b: f64,
+    pub ahead: Option<String>, // driver id finishing ahead, None for a tie
+    pub lap_delta_median: Option<f64>,
+}
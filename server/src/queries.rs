@@ -6,14 +6,208 @@ pub struct RoundQuery {
     pub round: i32,
 }
 
+/// options of the lap chart. filters are applied in the order of the fields.
 #[derive(Deserialize)]
 pub struct LapChartQuery {
-    pub year: i32,
-    pub round: i32,
+    pub drivers: Option<String>, // comma separated driver ids
+    pub from_lap: Option<i32>,
+    pub to_lap: Option<i32>,
+    #[serde(default)]
+    pub exclude_first_lap: bool,
+    #[serde(default)]
     pub exclude_pitstop: bool,
+    pub neutralized: Option<NeutralizedLaps>,
+    pub max_percent: Option<f64>, // e.g. 107 hides laps slower than 107% of the best lap
+    #[serde(default)]
+    pub percent_of: BestLap,
+    pub max_z_score: Option<f64>,
+}
+
+impl LapChartQuery {
+    pub fn driver_ids(&self) -> Option<Vec<String>> {
+        self.drivers.as_ref().map(|d| {
+            d.split(',')
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect()
+        })
+    }
+}
+
+/// best lap the `max_percent` threshold is relative to.
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BestLap {
+    #[default]
+    Field,
+    Driver,
+}
+
+/// how to treat safety car and virtual safety car laps in lap charts.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NeutralizedLaps {
+    Exclude,
+    Annotate,
+}
+
+/// runs of the championship simulation. a random seed is used if it is not set.
+#[derive(Deserialize)]
+pub struct SimulationQuery {
+    pub iterations: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+/// where the standings come from: Ergast API or computed from the stored results.
+#[derive(Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StandingsSource {
+    #[default]
+    Upstream,
+    Local,
+}
+
+#[derive(Deserialize)]
+pub struct StandingsQuery {
+    #[serde(default)]
+    pub source: StandingsSource,
+}
+
+/// fuel correction of lap times. `fuel_per_lap` (seconds per lap) overrides the configured rate.
+#[derive(Deserialize)]
+pub struct FuelQuery {
+    #[serde(default)]
+    pub fuel_corrected: bool,
+    pub fuel_per_lap: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct RaceTraceQuery {
+    pub reference_pace: Option<f64>, // seconds per lap. defaults to the winner's average lap
+}
+
+#[derive(Deserialize)]
+pub struct RaceStatsQuery {
+    pub year: i32,
+    pub round: Option<i32>, // statistics of the whole season if not set
 }
 
 #[derive(Deserialize)]
 pub struct YearQuery {
     pub year: i32,
 }
+
+/// points system to re-score a season with: "1991", "2003" or "2010".
+#[derive(Deserialize)]
+pub struct RescoreQuery {
+    pub system: String,
+    #[serde(default)]
+    pub fastest_lap: bool,
+    pub best_results: Option<usize>, // count only the best n results of each driver
+}
+
+#[derive(Deserialize)]
+pub struct HeadToHeadQuery {
+    pub driver_a: String,
+    pub driver_b: String,
+    pub season: Option<i32>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DriverSort {
+    Id,
+    GivenName,
+    #[default]
+    FamilyName,
+    DateOfBirth,
+    Nationality,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstructorSort {
+    Id,
+    #[default]
+    Name,
+    Nationality,
+}
+
+/// Paging, sorting and filtering parameters shared by `/drivers` and `/constructors`.
+/// `season` keeps entities that have a stored race result in that season and
+/// `search` is a case-insensitive substring match on id and name.
+#[derive(Deserialize)]
+pub struct ListQuery<S> {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub sort: S,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub nationality: Option<String>,
+    pub season: Option<i32>,
+    pub search: Option<String>,
+}
+
+impl<S> ListQuery<S> {
+    pub const DEFAULT_LIMIT: i64 = 100;
+    pub const MAX_LIMIT: i64 = 1000;
+
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+
+    // pattern for ILIKE, with the wildcard characters in the search text escaped
+    pub fn search_pattern(&self) -> Option<String> {
+        self.search.as_ref().map(|s| {
+            let escaped = s
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+    }
+}
+
+pub type DriverListQuery = ListQuery<DriverSort>;
+pub type ConstructorListQuery = ListQuery<ConstructorSort>;
+
+/// paging of the ratings leaderboard. `season` rates drivers as of the end of that season.
+#[derive(Deserialize)]
+pub struct RatingsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub season: Option<i32>,
+}
+
+impl RatingsQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DriverListQuery::DEFAULT_LIMIT)
+            .clamp(1, DriverListQuery::MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RecordsQuery {
+    pub season: Option<i32>,
+    pub constructor: Option<String>, // constructor id
+}